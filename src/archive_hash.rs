@@ -0,0 +1,424 @@
+//! Hash logs of archive members, so archived copies can be compared to the directories they came from
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek};
+
+use flate2::read::GzDecoder;
+use thiserror::Error;
+
+use crate::command::path_bytes;
+use crate::common::{self, LogFile, WriteToFileError};
+use crate::digest::{Digest, HashAlgorithm};
+use crate::log_entry::LogEntry;
+use crate::native_hash;
+use crate::sort;
+
+/// Settings for `hash_archive`
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ArchiveHashOptions {
+    /// The hash algorithms to record, in log column order
+    pub algorithms: Vec<HashAlgorithm>,
+    /// If true, member paths are logged relative to the archive root (e.g.: "./dir/file"), like
+    /// `hash --relative` paths
+    pub relative_paths: bool,
+}
+
+impl Default for ArchiveHashOptions {
+
+    /// Uses hashdeep's default algorithms, and logs member paths as stored.
+    fn default() -> Self {
+        ArchiveHashOptions{algorithms: HashAlgorithm::DEFAULT_COLUMNS.to_vec(), relative_paths: false}
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HashArchiveError {
+    #[error("\"{0}\" cannot be opened for reading (not found)")]
+    FileNotFound(String),
+    #[error("\"{0}\" cannot be opened for reading ({1})")]
+    Open(String, std::io::Error),
+    #[error("\"{0}\" is not a tar, tar.gz or zip archive")]
+    NotAnArchive(String),
+    #[error("\"{0}\" could not be read as a tar, tar.gz or zip archive ({1})")]
+    Read(String, String),
+}
+
+impl HashArchiveError {
+
+    fn open(e: std::io::Error, path: &str) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => HashArchiveError::FileNotFound(path.to_string()),
+            _ => HashArchiveError::Open(path.to_string(), e),
+        }
+    }
+}
+
+/// An error from reading an archive (converted to a `HashArchiveError` with the archive's path)
+enum HashArchiveReadError {
+    NotAnArchive,
+    Read(String),
+}
+
+impl From<zip::result::ZipError> for HashArchiveReadError {
+    fn from(e: zip::result::ZipError) -> Self {
+        HashArchiveReadError::Read(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for HashArchiveReadError {
+    fn from(e: std::io::Error) -> Self {
+        HashArchiveReadError::Read(e.to_string())
+    }
+}
+
+/// The archive formats that `hash_archive` reads
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum ArchiveFormat {
+    Tar,
+    GzipTar,
+    Zip,
+}
+
+impl ArchiveFormat {
+
+    /// Identifies an archive's format from its first bytes, or returns None if it isn't recognized.
+    ///
+    /// Tar files are recognized by the "ustar" magic of POSIX and GNU tar headers, or by an empty first
+    /// block (an empty archive). (Pre-POSIX tar files aren't recognized.)
+    fn detect(first_bytes: &[u8]) -> Option<Self> {
+        const TAR_BLOCK_SIZE: usize = 512;
+        const TAR_MAGIC: std::ops::Range<usize> = 257..262;
+
+        if first_bytes.starts_with(b"\x1f\x8b") {
+            Some(ArchiveFormat::GzipTar)
+        }
+        else if first_bytes.starts_with(b"PK\x03\x04") || first_bytes.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        }
+        else if first_bytes.len() >= TAR_BLOCK_SIZE &&
+            (&first_bytes[TAR_MAGIC] == b"ustar" || first_bytes[..TAR_BLOCK_SIZE].iter().all(|&x| x == 0)) {
+            Some(ArchiveFormat::Tar)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Generates a hash log from the members of an archive, without extracting it.
+///
+/// Writes the log of `hash_archive` to `output_path`.
+///
+/// # Errors
+///
+/// An error will be returned if
+/// * the output file already exists (this is checked before hashing; will not overwrite existing files)
+/// * the archive can't be read (see `hash_archive`)
+/// * any other error occurs while writing the output file
+pub fn run_archive_hash_command(
+    archive_path: &str,
+    output_path: &str,
+    options: &ArchiveHashOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+
+    //check the output path before hashing, which may take a long time
+    if std::fs::symlink_metadata(output_path).is_ok() {
+        return Err(WriteToFileError::OutputFileExists(output_path.to_string()).into());
+    }
+
+    let log_file = hash_archive(archive_path, options)?;
+    common::write_log_file_to_file(log_file, output_path)?;
+    Ok(())
+}
+
+/// Hashes the members of an archive, without extracting it.
+///
+/// The archive may be a tar file (optionally gzip-compressed) or a zip file: its format is
+/// identified from its contents, not its name.
+///
+/// The log has an entry for each regular file member (as `hash` logs only regular files),
+/// with the member's path as its filename: as stored in the archive, or, with
+/// `options.relative_paths`, as "./" + the path without any leading "./" or "/".
+/// Hard link members of tar files are logged with the contents of the members they link to.
+/// Entries are sorted by filename, and the log has a standard 5-line hashdeep header,
+/// so it can be compared to a directory's log with `part`.
+///
+/// # Errors
+///
+/// An error will be returned if the archive can't be opened, or if any part of it can't be read.
+pub fn hash_archive(archive_path: &str, options: &ArchiveHashOptions) -> Result<LogFile<Vec<LogEntry>>, HashArchiveError> {
+
+    let file = File::open(archive_path).map_err(|e| HashArchiveError::open(e, archive_path))?;
+
+    let mut entries = hash_archive_members(BufReader::new(file), &options.algorithms)
+        .map_err(|e| match e {
+            HashArchiveReadError::NotAnArchive => HashArchiveError::NotAnArchive(archive_path.to_string()),
+            HashArchiveReadError::Read(e) => HashArchiveError::Read(archive_path.to_string(), e),
+        })?;
+
+    for entry in &mut entries {
+        entry.filename = logged_member_path(&entry.filename, options.relative_paths);
+    }
+    sort::sort_entries(&mut entries);
+
+    let mut command_line = "hashdeep-compare hash-archive".to_string();
+    if options.algorithms != HashAlgorithm::DEFAULT_COLUMNS {
+        command_line += &format!(" --algorithms {}", HashAlgorithm::names(&options.algorithms));
+    }
+    if options.relative_paths {
+        command_line += " --relative";
+    }
+
+    //(the invocation directory is informational only: it's left empty if it's unavailable)
+    let invocation_directory = std::env::current_dir().unwrap_or_default();
+
+    Ok(LogFile {
+        entries,
+        header_warnings: vec![],
+        header_lines: vec![
+            b"%%%% HASHDEEP-1.0".to_vec(),
+            HashAlgorithm::format_line(&options.algorithms).into_bytes(),
+            [b"## Invoked from: ".as_slice(), &path_bytes(&invocation_directory)].concat(),
+            format!("## $ {command_line} {archive_path}").into_bytes(),
+            b"## ".to_vec(),
+        ],
+        hash_columns: options.algorithms.clone(),
+        invalid_lines: vec![],
+    })
+}
+
+/// Hashes an archive's regular file members (in archive order), logging their paths as stored.
+fn hash_archive_members<R: BufRead + Seek>(mut reader: R, algorithms: &[HashAlgorithm]) -> Result<Vec<LogEntry>, HashArchiveReadError> {
+
+    match ArchiveFormat::detect(reader.fill_buf()?) {
+        Some(ArchiveFormat::Tar) => hash_tar_members(reader, algorithms),
+        Some(ArchiveFormat::GzipTar) => hash_tar_members(GzDecoder::new(reader), algorithms),
+        Some(ArchiveFormat::Zip) => hash_zip_members(reader, algorithms),
+        None => Err(HashArchiveReadError::NotAnArchive),
+    }
+}
+
+fn hash_tar_members(reader: impl Read, algorithms: &[HashAlgorithm]) -> Result<Vec<LogEntry>, HashArchiveReadError> {
+
+    let mut archive = tar::Archive::new(reader);
+
+    //the contents of hashed members, for hard links to them (by path, without any leading "./" or "/":
+    // link targets aren't necessarily stored in the same form as member paths)
+    let mut contents = HashMap::<Vec<u8>, (u64, Vec<Digest>)>::new();
+    let mut entries = vec![];
+
+    for member in archive.entries()? {
+        let mut member = member?;
+        let filename = member.path_bytes().into_owned();
+        let entry_type = member.header().entry_type();
+
+        let (size, digests) = if entry_type.is_file() {
+            let (size, digests) = native_hash::hash_reader(&mut member, algorithms)?;
+            contents.insert(logged_member_path(&filename, true), (size, digests.clone()));
+            (size, digests)
+        }
+        else if entry_type.is_hard_link() {
+            let link_name = member.link_name_bytes().ok_or_else(|| HashArchiveReadError::Read("hard link without a target".to_string()))?;
+            contents.get(&logged_member_path(&link_name, true)).cloned()
+                .ok_or_else(|| HashArchiveReadError::Read(format!("hard link to missing member \"{}\"", String::from_utf8_lossy(&link_name))))?
+        }
+        else {
+            continue;
+        };
+
+        entries.push(LogEntry{size, digests, filename});
+    }
+
+    Ok(entries)
+}
+
+fn hash_zip_members(reader: impl Read + Seek, algorithms: &[HashAlgorithm]) -> Result<Vec<LogEntry>, HashArchiveReadError> {
+
+    const S_IFMT: u32 = 0o170000;
+    const S_IFLNK: u32 = 0o120000;
+
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entries = vec![];
+
+    for index in 0..archive.len() {
+        let mut member = archive.by_index(index)?;
+        let is_symlink = member.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK);
+        if !member.is_file() || is_symlink {
+            continue;
+        }
+
+        let filename = member.name_raw().to_vec();
+        let (size, digests) = native_hash::hash_reader(&mut member, algorithms)?;
+        entries.push(LogEntry{size, digests, filename});
+    }
+
+    Ok(entries)
+}
+
+/// Gets the path to log for an archive member.
+fn logged_member_path(member_path: &[u8], relative_paths: bool) -> Vec<u8> {
+
+    if !relative_paths {
+        return member_path.to_vec();
+    }
+
+    let mut path = member_path;
+    while let Some(rest) = path.strip_prefix(b"./").or_else(|| path.strip_prefix(b"/")) {
+        path = rest;
+    }
+    [b"./".as_slice(), path].concat()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    const MD5_1: &str = "c4ca4238a0b923820dcc509a6f75849b";
+    const MD5_22: &str = "b6d767d2f8ed5d21a44b0e5886680cb9";
+
+    fn md5_options(relative_paths: bool) -> ArchiveHashOptions {
+        ArchiveHashOptions{algorithms: vec![HashAlgorithm::Md5], relative_paths}
+    }
+
+    fn entry_lines(log_file: &LogFile<Vec<LogEntry>>) -> Vec<String> {
+        log_file.entries.iter().map(|entry| {
+            let mut line = vec![];
+            entry.write_to(&mut line).unwrap();
+            String::from_utf8(line).unwrap()
+        }).collect()
+    }
+
+    fn write_tar(writer: impl Write) {
+        let mut builder = tar::Builder::new(writer);
+
+        let mut append = |entry_type: tar::EntryType, path: &str, link_name: Option<&str>, contents: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            if let Some(link_name) = link_name {
+                header.set_link_name(link_name).unwrap();
+            }
+            builder.append_data(&mut header, path, contents).unwrap();
+        };
+
+        append(tar::EntryType::Directory, "./dir/", None, b"");
+        append(tar::EntryType::Regular, "./dir/b", None, b"22");
+        append(tar::EntryType::Regular, "./a", None, b"1");
+        append(tar::EntryType::Symlink, "./symlink", Some("./a"), b"");
+        append(tar::EntryType::Link, "./hardlink", Some("./a"), b"");
+
+        builder.into_inner().unwrap().flush().unwrap();
+    }
+
+    #[test]
+    fn archive_format_detect_test() {
+        let mut tar_header = [0u8; 512];
+        assert_eq!(ArchiveFormat::detect(&tar_header), Some(ArchiveFormat::Tar));
+        tar_header[..6].copy_from_slice(b"./dir/");
+        assert_eq!(ArchiveFormat::detect(&tar_header), None);
+        tar_header[257..263].copy_from_slice(b"ustar\0");
+        assert_eq!(ArchiveFormat::detect(&tar_header), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::detect(&tar_header[..511]), None);
+
+        assert_eq!(ArchiveFormat::detect(b"\x1f\x8b\x08\x00"), Some(ArchiveFormat::GzipTar));
+        assert_eq!(ArchiveFormat::detect(b"PK\x03\x04"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect(b"PK\x05\x06"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect(b""), None);
+    }
+
+    #[test]
+    fn logged_member_path_test() {
+        assert_eq!(logged_member_path(b"./dir/file", false), b"./dir/file");
+        assert_eq!(logged_member_path(b"dir/file", false), b"dir/file");
+        assert_eq!(logged_member_path(b"dir/file", true), b"./dir/file");
+        assert_eq!(logged_member_path(b"./dir/file", true), b"./dir/file");
+        assert_eq!(logged_member_path(b"/dir/file", true), b"./dir/file");
+        assert_eq!(logged_member_path(b".//./dir/file", true), b"./dir/file");
+        assert_eq!(logged_member_path(b"../file", true), b"./../file");
+        assert_eq!(logged_member_path(b"\xff", true), b"./\xff");
+    }
+
+    #[test]
+    fn hash_archive_tar_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let tar_path = temp_dir.path().join("archive.tar");
+        write_tar(File::create(&tar_path).unwrap());
+        let tgz_path = temp_dir.path().join("archive.tgz");
+        write_tar(flate2::write::GzEncoder::new(File::create(&tgz_path).unwrap(), flate2::Compression::default()));
+
+        for path in [&tar_path, &tgz_path] {
+            //(`tar::Builder` stores member paths without "./")
+            let log_file = hash_archive(path.to_str().unwrap(), &md5_options(false)).unwrap();
+            assert_eq!(entry_lines(&log_file), vec![
+                format!("1,{MD5_1},a"),
+                format!("2,{MD5_22},dir/b"),
+                format!("1,{MD5_1},hardlink"),
+            ]);
+            assert_eq!(log_file.hash_columns, vec![HashAlgorithm::Md5]);
+            assert_eq!(log_file.header_lines[1], b"%%%% size,md5,filename");
+            assert_eq!(log_file.header_lines.len(), 5);
+        }
+    }
+
+    #[test]
+    fn hash_archive_zip_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = temp_dir.path().join("archive.zip");
+
+        let mut writer = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.add_directory("dir/", options).unwrap();
+        writer.start_file("dir/b", options).unwrap();
+        writer.write_all(b"22").unwrap();
+        writer.start_file("a", options.compression_method(zip::CompressionMethod::Stored)).unwrap();
+        writer.write_all(b"1").unwrap();
+        writer.add_symlink("symlink", "a", options).unwrap();
+        writer.finish().unwrap();
+
+        let log_file = hash_archive(zip_path.to_str().unwrap(), &md5_options(true)).unwrap();
+        assert_eq!(entry_lines(&log_file), vec![
+            format!("1,{MD5_1},./a"),
+            format!("2,{MD5_22},./dir/b"),
+        ]);
+
+        let log_file = hash_archive(zip_path.to_str().unwrap(), &md5_options(false)).unwrap();
+        assert_eq!(entry_lines(&log_file), vec![
+            format!("1,{MD5_1},a"),
+            format!("2,{MD5_22},dir/b"),
+        ]);
+    }
+
+    #[test]
+    fn hash_archive_error_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let nonexistent = temp_dir.path().join("nonexistent");
+        assert!(matches!(hash_archive(nonexistent.to_str().unwrap(), &md5_options(false)), Err(HashArchiveError::FileNotFound(..))));
+        assert!(matches!(hash_archive(temp_dir.path().to_str().unwrap(), &md5_options(false)), Err(HashArchiveError::Read(..))));
+
+        let not_an_archive = temp_dir.path().join("not_an_archive");
+        std::fs::write(&not_an_archive, "not an archive: this text is longer than one 512-byte tar block ".repeat(10)).unwrap();
+        assert!(matches!(hash_archive(not_an_archive.to_str().unwrap(), &md5_options(false)), Err(HashArchiveError::NotAnArchive(..))));
+
+        let truncated_tar = temp_dir.path().join("truncated.tar");
+        write_tar(File::create(&truncated_tar).unwrap());
+        let tar_bytes = std::fs::read(&truncated_tar).unwrap();
+        std::fs::write(&truncated_tar, &tar_bytes[..1000]).unwrap();
+        assert!(matches!(hash_archive(truncated_tar.to_str().unwrap(), &md5_options(false)), Err(HashArchiveError::Read(..))));
+
+        let truncated_zip = temp_dir.path().join("truncated.zip");
+        std::fs::write(&truncated_zip, b"PK\x03\x04truncated").unwrap();
+        assert!(matches!(hash_archive(truncated_zip.to_str().unwrap(), &md5_options(false)), Err(HashArchiveError::Read(..))));
+
+        //checked before hashing
+        let output = temp_dir.path().join("output");
+        std::fs::write(&output, "").unwrap();
+        let err = run_archive_hash_command(nonexistent.to_str().unwrap(), output.to_str().unwrap(), &md5_options(false)).unwrap_err();
+        assert_eq!(err.to_string(), format!("{} exists (will not overwrite existing files)", output.to_str().unwrap()));
+    }
+}
@@ -0,0 +1,200 @@
+//! Comparing a hashdeep log against a live directory, without writing a second log first
+
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+use crate::command;
+use crate::common::{self, WriteToFileError};
+use crate::hash_errors::{HashError, HashErrorSummary};
+use crate::log_entry::LogEntry;
+use crate::native_hash::{self, NativeHashOptions, SkipFilter};
+use crate::partition;
+
+/// Settings for `audit_directory`
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct AuditOptions {
+    /// The number of worker threads that hash files
+    pub jobs: NonZeroUsize,
+    /// The maximum number of files that may be read at the same time (across all workers)
+    pub max_concurrent_reads: NonZeroUsize,
+    /// If true, paths are compared relative to the target directory (e.g.: "./dir/file")
+    pub relative_paths: bool,
+    /// If true, files that can't match any log entry (by size or by name) aren't read
+    pub skip_unmatchable: bool,
+}
+
+impl Default for AuditOptions {
+
+    /// Uses the native hashing engine's defaults, and reads all files.
+    fn default() -> Self {
+        let hash_options = NativeHashOptions::default();
+        AuditOptions {
+            jobs: hash_options.jobs,
+            max_concurrent_reads: hash_options.max_concurrent_reads,
+            relative_paths: false,
+            skip_unmatchable: false,
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct AuditSuccess
+{
+    /// Printable warning lines about the hashdeep log file, if any were emitted
+    pub log_warning_lines: Option<Vec<String>>,
+    /// The failures encountered while hashing the directory
+    pub error_summary: HashErrorSummary,
+    /// Printable statistics about the partitioning results
+    pub stats_string: String,
+}
+
+/// Gets the path of the file that lists the directory's unreadable files.
+fn errors_filename(output_filename_base: &str) -> String {
+    format!("{output_filename_base}_errors")
+}
+
+/// Gets the path of the file that lists the directory's files that weren't read (with `skip_unmatchable`).
+fn skipped_filename(output_filename_base: &str) -> String {
+    format!("{output_filename_base}_skipped_files_file2")
+}
+
+/// Partitions the entries of a hashdeep log against the files in a directory.
+///
+/// The directory is hashed (recursively, regular files only) with the built-in hashing engine,
+/// using the log's hash columns, and takes the place of the second log in `partition::partition_log`:
+/// the output files and statistics are the same, with "file 2" referring to the directory.
+/// For paths to match, the log must log them the same way (e.g.: hashed with the same target path,
+/// or with --relative and `options.relative_paths`).
+///
+/// Files that can't be read are listed in `output_filename_base` + "_errors" (in the same form as
+/// `hash` error files), and summarized in the result.
+///
+/// With `options.skip_unmatchable`, files whose size isn't in the log, and whose path isn't in the log,
+/// can't match any log entry: they aren't read, and are listed (as "size,path") in
+/// `output_filename_base` + "_skipped_files_file2" instead of in the no-match output file.
+///
+/// # Errors
+///
+/// An error will be returned if
+/// * any output file already exists (this is checked before hashing)
+/// * with `options.relative_paths`, `target_directory` is not a directory
+/// * any other error is emitted while reading or writing the files
+pub fn audit_directory(
+    log_filename: &str,
+    target_directory: &str,
+    output_filename_base: &str,
+    options: AuditOptions,
+) -> Result<AuditSuccess, Box<dyn std::error::Error>> {
+
+    let log_file = common::read_log_entries_from_file::<Vec<LogEntry>>(log_filename)?;
+
+    //check the output paths before hashing, which may take a long time
+    let mut output_filenames = partition::partition_output_filenames(output_filename_base).to_vec();
+    output_filenames.push(errors_filename(output_filename_base));
+    if options.skip_unmatchable {
+        output_filenames.push(skipped_filename(output_filename_base));
+    }
+    if let Some(x) = output_filenames.into_iter().find(|x| std::fs::symlink_metadata(x).is_ok()) {
+        return Err(WriteToFileError::OutputFileExists(x).into());
+    }
+
+    if options.relative_paths {
+        command::absolute_target_directory(target_directory)?;
+    }
+
+    let log_sizes = log_file.entries.iter().map(|x| x.size).collect::<HashSet<u64>>();
+    let log_filenames = log_file.entries.iter().map(|x| x.filename.as_slice()).collect::<HashSet<&[u8]>>();
+    let unmatchable = |filename: &[u8], size: u64| !log_sizes.contains(&size) && !log_filenames.contains(filename);
+
+    let hash_options = NativeHashOptions {
+        algorithms: log_file.hash_columns.clone(),
+        jobs: options.jobs,
+        max_concurrent_reads: options.max_concurrent_reads,
+        relative_paths: options.relative_paths,
+        save_mtimes: false,
+        previous_log: None,
+    };
+    let directory_hashes = native_hash::hash_directory(
+        target_directory,
+        &hash_options,
+        None,
+        options.skip_unmatchable.then_some(&unmatchable as SkipFilter));
+
+    let mut stats_string = partition::partition_entries(
+        &log_file.entries, &log_file.hash_columns,
+        &directory_hashes.log_entries, &log_file.hash_columns,
+        output_filename_base)?;
+
+    let mut error_summary = HashErrorSummary::new(errors_filename(output_filename_base));
+    let mut error_lines = Vec::<Vec<u8>>::new();
+    for (path, e) in directory_hashes.errors {
+        error_lines.push([path.as_slice(), format!(": {e}").as_bytes()].concat());
+        error_summary.push(HashError{path, kind: (&e).into()});
+    }
+    common::write_lines_to_file(error_lines, &error_summary.error_file_path)?;
+
+    if options.skip_unmatchable {
+        common::write_lines_to_file(
+            directory_hashes.skipped.iter().map(|(path, size)| [format!("{size},").as_bytes(), path].concat()),
+            &skipped_filename(output_filename_base))?;
+
+        stats_string.push_str(format!(" {} files in file 2 not read (no size or name match in file 1)\n", directory_hashes.skipped.len()).as_str());
+    }
+
+    Ok(AuditSuccess {
+        log_warning_lines: log_file.warning_report(),
+        error_summary,
+        stats_string,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn audit_directory_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let target_dir = temp_dir.path().join("target");
+        std::fs::create_dir_all(target_dir.join("sub")).unwrap();
+        std::fs::write(target_dir.join("unchanged"), "1").unwrap();
+        std::fs::write(target_dir.join("changed"), "22").unwrap();
+        std::fs::write(target_dir.join("sub/moved"), "3").unwrap();
+        std::fs::write(target_dir.join("new_same_size"), "6").unwrap();
+        std::fs::write(target_dir.join("new_other_size"), "55555").unwrap();
+
+        let log = temp_dir.path().join("log");
+        std::fs::write(&log, concat!(
+            "%%%% HASHDEEP-1.0\n",
+            "%%%% size,md5,filename\n",
+            "## Invoked from: /\n",
+            "## $ hashdeep -c md5 -l -r -o f .\n",
+            "## \n",
+            "1,c4ca4238a0b923820dcc509a6f75849b,./unchanged\n",
+            "1,c81e728d9d4c2f636f067f89cc14862c,./changed\n",
+            "1,eccbc87e4b5ce2fe28308fd9f2a7baf3,./moved\n",
+            "1,a87ff679a2f3e71d9181a67b7542122c,./deleted\n",
+        )).unwrap();
+
+        let output_base = temp_dir.path().join("audit").to_str().unwrap().to_string();
+        let options = AuditOptions{relative_paths: true, skip_unmatchable: true, ..Default::default()};
+        let success = audit_directory(log.to_str().unwrap(), target_dir.to_str().unwrap(), &output_base, options).unwrap();
+
+        let read = |suffix: &str| std::fs::read_to_string(format!("{output_base}_{suffix}")).unwrap();
+
+        assert!(read("full_match_pairs").contains("./unchanged"));
+        assert!(read("name_match_pairs").contains("2,b6d767d2f8ed5d21a44b0e5886680cb9,./changed"));
+        assert!(read("hashes_match_pairs").contains("./sub/moved"));
+        assert_eq!(read("no_match_entries_file1"), "1,a87ff679a2f3e71d9181a67b7542122c,./deleted\n");
+        assert_eq!(read("no_match_entries_file2"), "1,1679091c5a880faf6fb5e6087eb1b2dc,./new_same_size\n");
+        assert_eq!(read("skipped_files_file2"), "5,./new_other_size\n");
+        assert_eq!(read("errors"), "");
+
+        assert!(success.stats_string.contains(" 1 files in file 2 not read"));
+        assert_eq!(success.error_summary.summary_lines(), None);
+
+        //outputs are checked before hashing
+        let err = audit_directory(log.to_str().unwrap(), target_dir.to_str().unwrap(), &output_base, options).unwrap_err();
+        assert_eq!(err.to_string(), format!("{output_base}_full_match_pairs exists (will not overwrite existing files)"));
+    }
+}
@@ -0,0 +1,176 @@
+//! A compact list of the changes between two hashdeep logs (like `git status --short`), from a partition
+
+use crate::common::format_byte_count;
+use crate::log_entry::LogEntry;
+use crate::partitioner::match_group::{MatchGroup, SingleFileMatchGroup};
+use crate::partitioner::MatchPartition;
+
+/// Settings for `change_summary_lines`
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct ChangeSummaryOptions {
+    /// The number of changes to skip (from the start of the list)
+    pub offset: usize,
+    /// The maximum number of changes to list (if None, all of them are listed)
+    pub limit: Option<usize>,
+}
+
+/// A change between two logs, implied by a partition category
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Change<'a> {
+    /// A file created in file 2 (no match in file 1)
+    Created(&'a LogEntry),
+    /// A file deleted from file 1 (no match in file 2)
+    Deleted(&'a LogEntry),
+    /// A file whose content changed (name match pair)
+    Modified(&'a LogEntry, &'a LogEntry),
+    /// A file that moved or was renamed (hashes match pair)
+    Moved(&'a LogEntry, &'a LogEntry),
+    /// An entry of a match group (from file 1 or file 2), whose change is ambiguous
+    Ambiguous(&'a LogEntry, usize),
+}
+
+impl Change<'_> {
+
+    /// The key that changes are sorted by: the path, then the type of change (ambiguous entries of file 1,
+    /// then of file 2, created, deleted, modified, moved), then the new path of a move, then the size.
+    fn sort_key(&self) -> (&[u8], usize, &[u8], u64) {
+        match self {
+            Change::Ambiguous(x, file_number) => (&x.filename, *file_number, &[], x.size),
+            Change::Created(x) => (&x.filename, 3, &[], x.size),
+            Change::Deleted(x) => (&x.filename, 4, &[], x.size),
+            Change::Modified(x, _) => (&x.filename, 5, &[], x.size),
+            Change::Moved(x, y) => (&x.filename, 6, &y.filename, x.size),
+        }
+    }
+
+    /// Gets a printable line describing the change.
+    fn line(&self) -> String {
+        let path = |x: &LogEntry| String::from_utf8_lossy(&x.filename).to_string();
+        let size = |x: &LogEntry| format_byte_count(x.size);
+
+        match self {
+            Change::Created(x) => format!("A  {} ({})", path(x), size(x)),
+            Change::Deleted(x) => format!("D  {} ({})", path(x), size(x)),
+            Change::Modified(x, y) if x.size == y.size => format!("M  {} ({})", path(x), size(x)),
+            Change::Modified(x, y) => format!("M  {} ({} -> {})", path(x), size(x), size(y)),
+            Change::Moved(x, y) => format!("R  {} -> {} ({})", path(x), path(y), size(x)),
+            Change::Ambiguous(x, file_number) => format!("?  {} (file {file_number}, {})", path(x), size(x)),
+        }
+    }
+}
+
+/// Gets the changes that a partition implies, sorted by path (then by change).
+///
+/// Full match pairs are unchanged files, so they aren't listed.
+fn changes<'a>(mp: &MatchPartition<'a>) -> Vec<Change<'a>> {
+    let mut changes = vec![];
+
+    changes.extend(mp.no_match_file2.iter().map(|&x| Change::Created(x)));
+    changes.extend(mp.no_match_file1.iter().map(|&x| Change::Deleted(x)));
+    changes.extend(mp.name_match_pairs.iter().map(|x| Change::Modified(x.from_file1, x.from_file2)));
+    changes.extend(mp.hashes_match_pairs.iter().map(|x| Change::Moved(x.from_file1, x.from_file2)));
+
+    for groups in [&mp.full_match_groups, &mp.name_match_groups, &mp.hashes_match_groups] {
+        for MatchGroup{from_file1, from_file2} in groups {
+            changes.extend(from_file1.inner_ref().iter().map(|&x| Change::Ambiguous(x, 1)));
+            changes.extend(from_file2.inner_ref().iter().map(|&x| Change::Ambiguous(x, 2)));
+        }
+    }
+    for (groups, file_number) in [
+        (&mp.full_match_groups_file1, 1),
+        (&mp.full_match_groups_file2, 2),
+        (&mp.name_match_groups_file1, 1),
+        (&mp.name_match_groups_file2, 2),
+        (&mp.hashes_match_groups_file1, 1),
+        (&mp.hashes_match_groups_file2, 2),
+    ] {
+        for SingleFileMatchGroup{log_entries} in groups {
+            changes.extend(log_entries.inner_ref().iter().map(|&x| Change::Ambiguous(x, file_number)));
+        }
+    }
+
+    changes.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    changes
+}
+
+/// Gets printable lines listing the changes between two logs that a partition implies:
+/// `A` created, `D` deleted, `M` modified, `R` moved/renamed, and `?` ambiguous (an entry of a match group),
+/// with file sizes, sorted by path.
+///
+/// `options` selects a range of the list (e.g.: to page through a long list).
+pub fn change_summary_lines(mp: &MatchPartition, options: ChangeSummaryOptions) -> Vec<String> {
+    let changes = changes(mp);
+    let total = changes.len();
+
+    let mut lines = vec![];
+    if total == 0 {
+        lines.push("no changes".to_string());
+        return lines;
+    }
+
+    lines.push(format!("{total} changes (A: created, D: deleted, M: modified, R: moved/renamed, ?: ambiguous):"));
+    let shown = changes.iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .map(|x| x.line())
+        .collect::<Vec<_>>();
+    let shown_count = shown.len();
+    lines.extend(shown);
+
+    if shown_count < total {
+        match shown_count {
+            0 => lines.push(format!("(none shown: the offset is past the last of {total} changes)")),
+            _ => lines.push(format!("(showing {} to {} of {total} changes)", options.offset + 1, options.offset + shown_count)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::digest::HashAlgorithm;
+    use crate::partitioner::match_partition;
+
+    #[test]
+    fn change_summary_lines_test() {
+        let entry = |size: u64, hash: u64, filename: &str| {
+            LogEntry::from_bytes(format!("{size},{hash:032x},{filename}").as_bytes(), &[HashAlgorithm::Md5]).unwrap()
+        };
+        let file1 = [entry(1, 1, "same"), entry(2, 2, "deleted"), entry(3, 3, "modified"), entry(2048, 4, "old"),
+            entry(5, 5, "dup1"), entry(5, 5, "dup2"), entry(7, 7, "x")];
+        let file2 = [entry(1, 1, "same"), entry(10, 10, "created"), entry(30, 30, "modified"), entry(2048, 4, "new"),
+            entry(9, 9, "x"), entry(8, 8, "x")];
+        let from_file1 = file1.iter().collect::<Vec<_>>();
+        let from_file2 = file2.iter().collect::<Vec<_>>();
+        let mp = match_partition(&from_file1, &from_file2, &[HashAlgorithm::Md5]).unwrap();
+
+        assert_eq!(change_summary_lines(&mp, ChangeSummaryOptions::default()), vec![
+            "9 changes (A: created, D: deleted, M: modified, R: moved/renamed, ?: ambiguous):",
+            "A  created (10 bytes)",
+            "D  deleted (2 bytes)",
+            "?  dup1 (file 1, 5 bytes)",
+            "?  dup2 (file 1, 5 bytes)",
+            "M  modified (3 bytes -> 30 bytes)",
+            "R  old -> new (2.0 KiB)",
+            "?  x (file 1, 7 bytes)",
+            "?  x (file 2, 8 bytes)",
+            "?  x (file 2, 9 bytes)",
+        ]);
+
+        assert_eq!(change_summary_lines(&mp, ChangeSummaryOptions{offset: 1, limit: Some(2)}), vec![
+            "9 changes (A: created, D: deleted, M: modified, R: moved/renamed, ?: ambiguous):",
+            "D  deleted (2 bytes)",
+            "?  dup1 (file 1, 5 bytes)",
+            "(showing 2 to 3 of 9 changes)",
+        ]);
+
+        assert_eq!(change_summary_lines(&mp, ChangeSummaryOptions{offset: 9, limit: None}), vec![
+            "9 changes (A: created, D: deleted, M: modified, R: moved/renamed, ?: ambiguous):",
+            "(none shown: the offset is past the last of 9 changes)",
+        ]);
+
+        let empty = match_partition(&[], &[], &[HashAlgorithm::Md5]).unwrap();
+        assert_eq!(change_summary_lines(&empty, ChangeSummaryOptions::default()), vec!["no changes"]);
+    }
+}
@@ -1,11 +1,18 @@
-use std::process::{Command,Stdio};
-use std::fs::OpenOptions;
-use std::io::ErrorKind;
+use std::process::{Command,ExitStatus,Stdio};
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
 
 use thiserror::Error;
 use anyhow::anyhow;
 use which::which;
 
+use crate::common::ReadLogEntriesFromFileError;
+use crate::digest::HashAlgorithm;
+use crate::hash_errors::HashErrorSummary;
+use crate::partial_output::PartialFile;
+
 const CANNOT_FIND_BINARY_PATH_STR : &str = "external hashdeep binary cannot be found (is hashdeep installed?)";
 
 
@@ -27,6 +34,21 @@ pub enum RunHashdeepCommandError {
     #[error("\"{0}\" cannot be opened for writing ({})", .1)]
     OutputFileOtherError(String, #[source] std::io::Error),
 
+    #[error("\"{0}\" cannot be hashed with relative paths ({1})")]
+    RelativeTargetDirectory(String, #[source] std::io::Error),
+
+    #[error("\"{0}\" cannot be read ({1}): a previous log can only be used with its modification time file (see --save-mtimes)")]
+    PreviousMtimesFile(String, #[source] std::io::Error),
+
+    #[error(transparent)]
+    PreviousLog(#[from] ReadLogEntriesFromFileError),
+
+    #[error("\"{0}\" is not a hashdeep binary (only hashdeep writes logs that hashdeep-compare can read)")]
+    NotAHashdeepBinary(String),
+
+    #[error("hashdeep did not finish successfully ({0})")]
+    HashdeepFailed(ExitStatus, HashErrorSummary),
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
@@ -36,7 +58,7 @@ pub enum RunHashdeepCommandError {
 
 impl RunHashdeepCommandError {
 
-    fn new(e: std::io::Error, path: &str) -> Self {
+    pub(crate) fn new(e: std::io::Error, path: &str) -> Self {
 
         match e.kind() {
             ErrorKind::AlreadyExists => RunHashdeepCommandError::OutputFileExists(path.to_string()),
@@ -46,76 +68,276 @@ impl RunHashdeepCommandError {
     }
 }
 
-/// Runs hashdeep with the settings recommended for hashdeep-compare.
+/// The file types hashdeep can be limited to with `-o` (see `HashdeepOptions::file_types`)
+pub const HASHDEEP_FILE_TYPES: &str = "fbcplsde";
+
+/// Settings for running the external hashdeep binary
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HashdeepOptions {
+    /// The hashdeep binary to run (a name on the PATH, or a path)
+    pub binary: String,
+    /// The hash algorithms to record (hashdeep's `-c`)
+    pub algorithms: Vec<HashAlgorithm>,
+    /// The number of hashing threads (hashdeep's `-j`), or None for hashdeep's default
+    pub jobs: Option<NonZeroUsize>,
+    /// The file types to hash (hashdeep's `-o`): any of the letters in `HASHDEEP_FILE_TYPES`
+    pub file_types: String,
+    /// If true, hashdeep is run from inside the target directory, so logged paths are relative to it
+    pub relative_paths: bool,
+}
+
+impl Default for HashdeepOptions {
+
+    /// The settings recommended for hashdeep-compare: `hashdeep -o f`, with hashdeep's default algorithms
+    fn default() -> Self {
+        HashdeepOptions {
+            binary: "hashdeep".to_string(),
+            algorithms: HashAlgorithm::DEFAULT_COLUMNS.to_vec(),
+            jobs: None,
+            file_types: "f".to_string(),
+            relative_paths: false,
+        }
+    }
+}
+
+impl HashdeepOptions {
+
+    /// Gets the arguments to pass to hashdeep.
+    ///
+    /// Options that match hashdeep's defaults are omitted.
+    /// With `relative_paths`, the target is "." (hashdeep is run from inside `target_directory`).
+    pub fn args(&self, target_directory: &str) -> Vec<String> {
+
+        let target_directory = match self.relative_paths {
+            true => ".",
+            false => target_directory,
+        };
+
+        let mut args = vec!["-l".to_string(), "-r".to_string()];
+
+        if self.algorithms != HashAlgorithm::DEFAULT_COLUMNS {
+            args.extend(["-c".to_string(), HashAlgorithm::names(&self.algorithms)]);
+        }
+        if let Some(jobs) = self.jobs {
+            args.extend(["-j".to_string(), jobs.to_string()]);
+        }
+        args.extend(["-o".to_string(), self.file_types.clone()]);
+        args.push(target_directory.to_string());
+
+        args
+    }
+
+    /// Confirms that `binary` is a hashdeep binary, by its file name (e.g.: "hashdeep", "hashdeep64.exe").
+    ///
+    /// The other programs in hashdeep's family (md5deep, sha1deep, etc.) are the same binary under different
+    /// names, and write a different log format depending on which name they are run with.
+    ///
+    /// # Errors
+    ///
+    /// `NotAHashdeepBinary` will be returned if the file name doesn't start with "hashdeep".
+    pub fn check_binary(&self) -> Result<(), RunHashdeepCommandError> {
+
+        let is_hashdeep = Path::new(&self.binary).file_name()
+            .map(|x| x.to_string_lossy().to_lowercase().starts_with("hashdeep"))
+            .unwrap_or(false);
+
+        match is_hashdeep {
+            true => Ok(()),
+            false => Err(RunHashdeepCommandError::NotAHashdeepBinary(self.binary.clone())),
+        }
+    }
+}
+
+/// Reads a set of hashdeep file type letters (for `HashdeepOptions::file_types`).
 ///
-/// The log includes (recursively) all files and directories in `target_directory`,
-/// and is written to `output_path_base`, with hashdeep's stderr
-/// written to `output_path_base` + ".errors".
+/// # Errors
+///
+/// An error message will be returned if the set is empty, or if any letter is unrecognized or repeated.
+pub fn parse_file_types(s: &str) -> Result<String, String> {
+
+    if s.is_empty() {
+        return Err("at least 1 file type is required".to_string());
+    }
+    for (i, c) in s.char_indices() {
+        if !HASHDEEP_FILE_TYPES.contains(c) {
+            return Err(format!("unknown file type '{c}' (expected any of: {HASHDEEP_FILE_TYPES})"));
+        }
+        if s[..i].contains(c) {
+            return Err(format!("file type '{c}' is repeated"));
+        }
+    }
+    Ok(s.to_string())
+}
+
+/// Gets the absolute path of a target directory, to hash from inside it.
 ///
 /// # Errors
 ///
-/// An error will be returned if
-/// * the `hashdeep` command is not available
-/// * the output log file or error file already exist (will not overwrite existing files)
-/// * any other error occurs while creating the output files
-/// * any other error occurs when running `hashdeep`
-pub fn run_hashdeep_command(
-    target_directory: &str,
-    output_path_base: &str,
-    hashdeep_command_name: &str,
-) -> Result<(), RunHashdeepCommandError> {
+/// `RelativeTargetDirectory` will be returned if the path can't be resolved, or isn't a directory.
+pub(crate) fn absolute_target_directory(target_directory: &str) -> Result<PathBuf, RunHashdeepCommandError> {
 
-    //confirm availability of external hashdeep binary
-    match which(hashdeep_command_name) {
-        Err(which::Error::CannotFindBinaryPath) => return Err(RunHashdeepCommandError::CannotFindBinaryPath),
-        Err(x) => return Err(anyhow!(x).into()),
-        _ => ()
-    };
+    let error = |e| RunHashdeepCommandError::RelativeTargetDirectory(target_directory.to_string(), e);
 
-    let error_log_suffix = ".errors";
+    let path = std::fs::canonicalize(target_directory).map_err(error)?;
+    match path.is_dir() {
+        true => Ok(path),
+        false => Err(error(ErrorKind::NotADirectory.into())),
+    }
+}
 
-    let output_error_path = format!("{output_path_base}{error_log_suffix}");
+/// Creates the 3rd hashdeep log header line for a log with relative paths:
+/// the absolute target directory (which the log was generated from), and the host name.
+pub(crate) fn relative_invocation_line(absolute_target_directory: &Path) -> Vec<u8> {
+    let mut line = b"## Invoked from: ".to_vec();
+    line.extend(path_bytes(absolute_target_directory));
+    line.extend(format!(" (host: {})", gethostname::gethostname().to_string_lossy()).into_bytes());
+    line
+}
 
+/// Copies a hashdeep log, replacing its "## Invoked from: " header line with `invocation_line`.
+fn copy_with_invocation_line(log: &mut impl BufRead, output: &mut impl Write, invocation_line: &[u8]) -> std::io::Result<()> {
 
-    //try to open both output files
-    let maybe_output_file =
-        OpenOptions::new().write(true).create_new(true).open(output_path_base)
-            .map_err(|e| RunHashdeepCommandError::new(e, output_path_base));
+    //the invocation line is the 3rd line of the 5-line header
+    let mut line = Vec::new();
+    for _ in 0..5 {
+        line.clear();
+        if log.read_until(b'\n', &mut line)? == 0 {break;}
 
-    let maybe_error_file  =
-        OpenOptions::new().write(true).create_new(true).open(&output_error_path)
-            .map_err(|e| RunHashdeepCommandError::new(e, &output_error_path));
+        if line.starts_with(b"## Invoked from: ") {
+            output.write_all(invocation_line)?;
+            output.write_all(b"\n")?;
+        }
+        else {
+            output.write_all(&line)?;
+        }
+    }
 
+    std::io::copy(log, output)?;
+    output.flush()
+}
 
-    let (output_file, error_file) =
+/// Gets a path's exact bytes (where the platform allows it), as hashdeep would write them.
+pub(crate) fn path_bytes(path: &Path) -> Vec<u8> {
 
-    match (maybe_output_file, maybe_error_file) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    }
 
-        (Ok(output_file), Ok(error_file)) => (output_file, error_file),
+    #[cfg(not(unix))]
+    {
+        path.to_string_lossy().into_owned().into_bytes()
+    }
+}
 
+/// Gets the path whose bytes (as returned by `path_bytes`) are `bytes`.
+///
+/// (On non-Unix platforms, `bytes` is read as UTF-8, with invalid sequences replaced.)
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
 
-        //if either file failed to open, abort the command and clean up:
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
 
-        (Err(output_file_error), Ok(_)) => {
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
 
-            //delete the file that was successfully created
-            std::fs::remove_file(&output_error_path)?;
+/// Gets the path of the error file that accompanies a hash log.
+pub(crate) fn error_file_path(output_path_base: &str) -> String {
+    format!("{output_path_base}.errors")
+}
 
-            return Err(output_file_error);
-        },
+/// Gets the path that the error file of a failed hashdeep run is kept at.
+///
+/// Unlike the error file's path, it isn't checked before hashing, so it doesn't block a later run.
+pub(crate) fn failed_error_file_path(output_path_base: &str) -> String {
+    format!("{output_path_base}.errors.failed")
+}
 
-        (Ok(_), Err(error_file_error)) => {
+/// A hash log file and its error file, opened for writing at temporary (".partial") paths
+pub(crate) struct HashOutputFiles {
+    pub output_file: File,
+    pub error_file: File,
+    partial_output_file: PartialFile,
+    partial_error_file: PartialFile,
+}
 
-            //delete the file that was successfully created
-            std::fs::remove_file(output_path_base)?;
+impl HashOutputFiles {
 
-            return Err(error_file_error);
-        },
+    /// The temporary path of the error file, while it is being written
+    pub fn error_file_partial_path(&self) -> &Path {
+        self.partial_error_file.partial_path()
+    }
+
+    /// Moves both files to their final paths.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while moving the files will be returned.
+    pub fn commit(self) -> std::io::Result<()> {
+        drop(self.output_file);
+        drop(self.error_file);
+        self.partial_error_file.commit()?;
+        self.partial_output_file.commit()
+    }
+
+    /// Moves the error file to `path` (replacing any file there), and removes the output log file.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while moving the error file will be returned.
+    pub fn keep_error_file_at(self, path: &str) -> std::io::Result<()> {
+        drop(self.output_file);
+        drop(self.error_file);
+        self.partial_error_file.commit_replacing(Path::new(path))
+    }
+}
+
+/// Creates a hash log file for `output_path_base` and its error file (for `output_path_base` + ".errors").
+///
+/// The files are written at temporary paths: they will only appear at their final paths when
+/// `HashOutputFiles::commit` is called, and they are removed if it isn't (including on Ctrl-C).
+/// If either file can't be created, neither will be left behind.
+///
+/// # Errors
+///
+/// An error will be returned if
+/// * the output log file or error file already exist (will not overwrite existing files)
+/// * any other error occurs while creating the output files
+pub(crate) fn open_output_files(output_path_base: &str) -> Result<HashOutputFiles, RunHashdeepCommandError> {
+
+    let output_error_path = error_file_path(output_path_base);
+
+
+    //try to open both output files
+    let maybe_output_file = PartialFile::create(output_path_base)
+        .map_err(|(e, path)| RunHashdeepCommandError::new(e, &path));
+
+    let maybe_error_file = PartialFile::create(&output_error_path)
+        .map_err(|(e, path)| RunHashdeepCommandError::new(e, &path));
+
+
+    //if either file failed to open, abort the command
+    //  (the file that was successfully created is removed when it is dropped)
+    match (maybe_output_file, maybe_error_file) {
+
+        (Ok((partial_output_file, output_file)), Ok((partial_error_file, error_file))) =>
+            Ok(HashOutputFiles{output_file, error_file, partial_output_file, partial_error_file}),
+
+        (Err(output_file_error), Ok(_)) => Err(output_file_error),
+
+        (Ok(_), Err(error_file_error)) => Err(error_file_error),
 
         (Err(output_file_error), Err(error_file_error)) => {
 
             //if present, combine 2 OutputFileExists errors into 1 OutputFilesExist error
-            return Err(
+            Err(
                 if let ( RunHashdeepCommandError::OutputFileExists(file1),
                          RunHashdeepCommandError::OutputFileExists(file2) )
                         = (&output_file_error, &error_file_error)
@@ -126,23 +348,103 @@ pub fn run_hashdeep_command(
                     //otherwise, just return the output file's error
                     output_file_error
                 }
-            );
+            )
         }
+    }
+}
+
+/// Runs hashdeep with the settings recommended for hashdeep-compare, adjusted by `options`.
+///
+/// The log includes (recursively) all files of the types in `options.file_types` (by default,
+/// regular files) in `target_directory`, and is written to `output_path_base`, with hashdeep's stderr
+/// written to `output_path_base` + ".errors".
+///
+/// With `options.relative_paths`, hashdeep is run from inside `target_directory`, so logged paths
+/// are relative to it (e.g.: "./dir/file"), and the log header records the absolute target directory
+/// and the host name.
+///
+/// The files are written at temporary paths, and only moved to these paths if hashdeep succeeds:
+/// if it fails (or the program is interrupted), they are removed. If hashdeep fails after reporting errors,
+/// the error file is kept at `output_path_base` + ".errors.failed" instead (replacing one from an earlier failure),
+/// so it can be read, but doesn't prevent the command from being run again.
+///
+/// On success, returns a summary of the failures hashdeep reported in the error file (if any).
+///
+/// # Errors
+///
+/// An error will be returned if
+/// * `options.binary` is not a hashdeep binary, or is not available
+/// * with `options.relative_paths`, `target_directory` is not a directory
+/// * the output log file or error file already exist (will not overwrite existing files)
+/// * any other error occurs while creating the output files
+/// * any other error occurs when running `hashdeep`
+/// * `hashdeep` exits with a failure status
+pub fn run_hashdeep_command(
+    target_directory: &str,
+    output_path_base: &str,
+    options: &HashdeepOptions,
+) -> Result<HashErrorSummary, RunHashdeepCommandError> {
+
+    options.check_binary()?;
+
+    //confirm availability of external hashdeep binary
+    match which(&options.binary) {
+        Err(which::Error::CannotFindBinaryPath) => return Err(RunHashdeepCommandError::CannotFindBinaryPath),
+        Err(x) => return Err(anyhow!(x).into()),
+        _ => ()
     };
 
-    Command::new(hashdeep_command_name)
+    let relative_root = match options.relative_paths {
+        true => Some(absolute_target_directory(target_directory)?),
+        false => None,
+    };
 
-    .arg("-l")
-    .arg("-r")
-    .arg("-o").arg("f")
-    .arg(target_directory)
+    let output_files = open_output_files(output_path_base)?;
+
+    let mut command = Command::new(&options.binary);
+    command
+    .args(options.args(target_directory))
 
     .stdin(Stdio::null())
-    .stdout(output_file)
-    .stderr(error_file)
+    .stderr(output_files.error_file.try_clone()?);
+
+    let status = match &relative_root {
+        None => command.stdout(output_files.output_file.try_clone()?).status()?,
+        Some(relative_root) => {
+            //hashdeep's log is copied through, to add the host name to its header
+            let mut child = command.current_dir(relative_root).stdout(Stdio::piped()).spawn()?;
+            let mut log = BufReader::new(child.stdout.take().expect("child stdout should be piped"));
+
+            let copied = copy_with_invocation_line(
+                &mut log,
+                &mut BufWriter::new(&output_files.output_file),
+                &relative_invocation_line(relative_root));
+
+            if let Err(e) = copied {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e.into());
+            }
+            child.wait()?
+        },
+    };
+
+    let mut error_summary = HashErrorSummary::from_file(output_files.error_file_partial_path())?;
+
+    //on failure, both files are removed from their paths, but a nonempty error file is kept as a diagnostic
+    //  (at a path that won't block the next run)
+    if !status.success() {
+        error_summary.error_file_path = failed_error_file_path(output_path_base);
+        if error_summary.summary_lines().is_some() {
+            output_files.keep_error_file_at(&error_summary.error_file_path)?;
+        }
+        return Err(RunHashdeepCommandError::HashdeepFailed(status, error_summary));
+    }
 
-    .status()?;
-    Ok(())
+    error_summary.error_file_path = error_file_path(output_path_base);
+
+    output_files.commit()?;
+    Ok(error_summary)
 }
 
 #[cfg(test)]
@@ -159,8 +461,138 @@ mod tests {
 
             run_hashdeep_command("fake_target_dir",
                                  "fake_output_path_base",
-                                 "nonexistent_program_name_Cmn2TMmwGO9U2j7")
+                                 &HashdeepOptions{
+                                     binary: "hashdeep_nonexistent_program_name_Cmn2TMmwGO9U2j7".to_string(),
+                                     ..Default::default()
+                                 })
             .unwrap_err().to_string()
         );
     }
-}
\ No newline at end of file
+
+    #[cfg(unix)]
+    #[test]
+    fn run_hashdeep_command_failure_status_test() {
+
+        //`false` ignores its arguments and exits with a failure status
+        //  (linked under a hashdeep name, to pass the binary name check)
+        let bin_dir = tempfile::tempdir().unwrap();
+        let fake_hashdeep = bin_dir.path().join("hashdeep-false");
+        std::os::unix::fs::symlink(which("false").unwrap(), &fake_hashdeep).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        let options = HashdeepOptions{binary: fake_hashdeep.to_str().unwrap().to_string(), ..Default::default()};
+        let err = run_hashdeep_command("fake_target_dir", temp_file_path_str, &options).unwrap_err();
+
+        assert!(matches!(err, RunHashdeepCommandError::HashdeepFailed(status, _) if status.code() == Some(1)));
+        assert_eq!(err.to_string(), "hashdeep did not finish successfully (exit status: 1)");
+
+        //no output files are left behind
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_hashdeep_command_failure_with_errors_test() {
+        use std::os::unix::fs::PermissionsExt;
+
+        //a script that reports an error and exits with a failure status
+        let bin_dir = tempfile::tempdir().unwrap();
+        let fake_hashdeep = bin_dir.path().join("hashdeep-fail");
+        std::fs::write(&fake_hashdeep, "#!/bin/sh\necho 'fake_target_dir: No such file or directory' >&2\nexit 1\n").unwrap();
+        std::fs::set_permissions(&fake_hashdeep, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        let options = HashdeepOptions{binary: fake_hashdeep.to_str().unwrap().to_string(), ..Default::default()};
+        let err = run_hashdeep_command("fake_target_dir", temp_file_path_str, &options).unwrap_err();
+
+        let RunHashdeepCommandError::HashdeepFailed(_, error_summary) = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(error_summary.error_file_path, format!("{temp_file_path_str}.errors.failed"));
+        assert_eq!(error_summary.errors.len(), 1);
+
+        //the error file that the summary refers to is kept (at a path that isn't checked), but the output paths are free
+        assert_eq!(std::fs::read_to_string(&error_summary.error_file_path).unwrap(), "fake_target_dir: No such file or directory\n");
+        assert!(!temp_file.exists());
+        assert!(!Path::new(&format!("{temp_file_path_str}.errors")).exists());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        //so the command can be run again (replacing the kept error file)
+        let err = run_hashdeep_command("fake_target_dir", temp_file_path_str, &options).unwrap_err();
+        assert!(matches!(err, RunHashdeepCommandError::HashdeepFailed(..)));
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn args_test() {
+        let options = HashdeepOptions::default();
+        assert_eq!(options.args("target"), ["-l", "-r", "-o", "f", "target"]);
+
+        let options = HashdeepOptions {
+            binary: "hashdeep64".to_string(),
+            algorithms: vec![HashAlgorithm::Sha1, HashAlgorithm::Tiger],
+            jobs: NonZeroUsize::new(4),
+            file_types: "fl".to_string(),
+            relative_paths: false,
+        };
+        assert_eq!(options.args("target"), ["-l", "-r", "-c", "sha1,tiger", "-j", "4", "-o", "fl", "target"]);
+
+        let options = HashdeepOptions{relative_paths: true, ..Default::default()};
+        assert_eq!(options.args("target"), ["-l", "-r", "-o", "f", "."]);
+    }
+
+    #[test]
+    fn copy_with_invocation_line_test() {
+        let log = concat!(
+            "%%%% HASHDEEP-1.0\n",
+            "%%%% size,md5,sha256,filename\n",
+            "## Invoked from: /mnt/volume\n",
+            "## $ hashdeep -l -r -o f .\n",
+            "## \n",
+            "## Invoked from: a file path, not a header line\n",
+        );
+
+        let mut output = Vec::new();
+        copy_with_invocation_line(&mut log.as_bytes(), &mut output, b"## Invoked from: /mnt/volume (host: h)").unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), log.replacen("/mnt/volume", "/mnt/volume (host: h)", 1));
+    }
+
+    #[test]
+    fn absolute_target_directory_test() {
+        let path = absolute_target_directory("tests/hashdeep_target").unwrap();
+        assert!(path.is_absolute());
+        assert!(path.ends_with("tests/hashdeep_target"));
+
+        assert!(matches!(absolute_target_directory("tests/test1.txt"), Err(RunHashdeepCommandError::RelativeTargetDirectory(..))));
+        assert!(matches!(absolute_target_directory("tests/does_not_exist"), Err(RunHashdeepCommandError::RelativeTargetDirectory(..))));
+    }
+
+    #[test]
+    fn check_binary_test() {
+        for binary in ["hashdeep", "hashdeep64", "/usr/local/bin/hashdeep", "Hashdeep64.exe"] {
+            let options = HashdeepOptions{binary: binary.to_string(), ..Default::default()};
+            assert!(options.check_binary().is_ok(), "{binary}");
+        }
+        for binary in ["md5deep", "/usr/bin/sha256deep", "", "/usr/bin/"] {
+            let options = HashdeepOptions{binary: binary.to_string(), ..Default::default()};
+            assert!(matches!(options.check_binary(), Err(RunHashdeepCommandError::NotAHashdeepBinary(_))), "{binary}");
+        }
+    }
+
+    #[test]
+    fn parse_file_types_test() {
+        assert_eq!(parse_file_types("f"), Ok("f".to_string()));
+        assert_eq!(parse_file_types("flp"), Ok("flp".to_string()));
+
+        assert!(parse_file_types("").is_err());
+        assert!(parse_file_types("fx").is_err());
+        assert!(parse_file_types("ff").is_err());
+    }
+}
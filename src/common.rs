@@ -1,11 +1,13 @@
-use std::fs::{File,OpenOptions,read_to_string};
-use std::io::{Write, ErrorKind};
+use std::fs::{File,OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write, ErrorKind};
 use std::fmt::{Display, Formatter};
+use std::borrow::Borrow;
 
 use thiserror::Error;
-use peeking_take_while::PeekableExt;
 
+use crate::digest::HashAlgorithm;
 use crate::log_entry::LogEntry;
+use crate::partitioner::directory_move::DirectoryMove;
 use crate::partitioner::match_pair::MatchPair;
 use crate::partitioner::match_group::{SingleFileMatchGroup,MatchGroup};
 use crate::some_vec::SomeVec;
@@ -114,7 +116,7 @@ fn check_hashdeep_log_header(header_lines: &[String]) -> Vec<HashdeepLogHeaderWa
     }
 
     match header_lines.get(1) {
-        Some(x) if x == "%%%% size,md5,sha256,filename" => {},
+        Some(x) if HashAlgorithm::columns_from_format_line(x).is_some() => {},
         Some(x) => warnings.push(HashdeepLogHeaderWarning::UntestedLogFormat(x.clone())),
         None => {}
     }
@@ -134,6 +136,51 @@ fn check_hashdeep_log_header(header_lines: &[String]) -> Vec<HashdeepLogHeaderWa
     warnings
 }
 
+/// Gets the hash columns of a hashdeep log from its header.
+///
+/// If the header doesn't list a recognized set of columns, hashdeep's default columns are assumed.
+fn hash_columns_from_header(header_lines: &[String]) -> Vec<HashAlgorithm> {
+
+    header_lines.get(1)
+        .and_then(|x| HashAlgorithm::columns_from_format_line(x))
+        .unwrap_or_else(|| HashAlgorithm::DEFAULT_COLUMNS.to_vec())
+}
+
+
+/// Parses a byte count argument (e.g.: `verify --bytes`, `sort --memory-limit`): a whole number, optionally followed by K, M, G or T (binary units: K = 1024).
+pub fn parse_byte_count(s: &str) -> Result<u64, String> {
+    let error = || format!("\"{s}\" is not a byte count (e.g.: 500000, 512K, 100M, 2G, 1T)");
+
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        Some((i, 'T' | 't')) => (&s[..i], 1 << 40),
+        _ => (s, 1),
+    };
+
+    number.parse::<u64>().ok()
+        .and_then(|x| x.checked_mul(multiplier))
+        .ok_or_else(error)
+}
+
+/// Formats a byte count for printing: in bytes below 1 KiB, otherwise in binary units with 1 decimal place
+/// (e.g.: "512 bytes", "1.5 KiB", "100.0 MiB").
+pub fn format_byte_count(count: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if count < 1 << 10 {
+        return format!("{count} bytes");
+    }
+    let mut value = count as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
 
 /// The result of successfully reading a hashdeep log:
 /// its entries, plus load-time header warnings and entry parse failures (if any)
@@ -143,8 +190,11 @@ pub struct LogFile<T>
 {
     pub entries: T,
     pub header_warnings: Vec<HashdeepLogHeaderWarning>,
-    pub header_lines: Vec<String>,
-    pub invalid_lines: Vec<String>,
+    /// The header lines' exact bytes (without line endings)
+    pub header_lines: Vec<Vec<u8>>,
+    /// The hash columns of each entry (between the size and the filename), as listed in the header
+    pub hash_columns: Vec<HashAlgorithm>,
+    pub invalid_lines: Vec<Vec<u8>>,
 }
 
 impl<T> LogFile<T>
@@ -152,22 +202,168 @@ impl<T> LogFile<T>
 {
     ///Returns a Vec of printable warning lines (or None, if no warnings or invalid lines exist)
     pub fn warning_report(&self) -> Option<Vec<String>> {
+        warning_report(&self.header_warnings, self.invalid_lines.len())
+    }
+}
+
+fn warning_report(header_warnings: &[HashdeepLogHeaderWarning], invalid_line_count: usize) -> Option<Vec<String>> {
 
-        let mut lines = self.header_warnings.iter().map(
-            |w| w.to_string()
-        ).collect::<Vec<String>>();
+    let mut lines = header_warnings.iter().map(
+        |w| w.to_string()
+    ).collect::<Vec<String>>();
 
-        match self.invalid_lines.len() {
-            0 => {},
-            1 => lines.push("1 invalid log entry detected".to_string()),
-            x => lines.push(format!("{x} invalid log entries detected"))
+    match invalid_line_count {
+        0 => {},
+        1 => lines.push("1 invalid log entry detected".to_string()),
+        x => lines.push(format!("{x} invalid log entries detected"))
+    }
+
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines)
+    }
+}
+
+/// A hashdeep log line that could not be read as a log entry
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct InvalidLine(pub Vec<u8>);
+
+/// Reads a hashdeep log incrementally from a `BufRead` source.
+///
+/// The header is read on creation; after that, the reader yields the log's entry lines one at a time,
+/// each as a parsed `LogEntry` or an `InvalidLine`, so the log's text never needs to be held in memory.
+pub struct LogReader<R: BufRead> {
+    reader: R,
+    header_lines: Vec<Vec<u8>>,
+    header_warnings: Vec<HashdeepLogHeaderWarning>,
+    hash_columns: Vec<HashAlgorithm>,
+    /// The first entry line, if it was read while looking for the end of the header
+    pending_line: Option<Vec<u8>>,
+    invalid_line_count: usize,
+}
+
+impl LogReader<BufReader<File>> {
+
+    /// Opens a hashdeep log file and reads its header.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while opening the file or reading its header will be returned.
+    pub fn open(filename: &str) -> Result<Self, ReadLogEntriesFromFileError> {
+
+        File::open(filename)
+            .and_then(|file| LogReader::new(BufReader::new(file)))
+            .map_err(|e| ReadLogEntriesFromFileError::new(e, filename))
+    }
+}
+
+impl<R: BufRead> LogReader<R> {
+
+    /// Creates a LogReader and reads the log's header from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while reading the header will be returned.
+    pub fn new(mut reader: R) -> std::io::Result<Self> {
+
+        //collect the header lines based on expected prefix symbols
+        let mut header_lines = Vec::<Vec<u8>>::new();
+        let mut pending_line = None;
+
+        while let Some(line) = read_line(&mut reader)? {
+            if line.starts_with(b"%%%%") || line.starts_with(b"##") {
+                header_lines.push(line);
+            }
+            else {
+                pending_line = Some(line);
+                break;
+            }
         }
 
-        match lines.is_empty() {
-            true => None,
-            false => Some(lines)
+        // header lines are only checked as text: any invalid UTF-8 will be reported in warnings
+        let header_text = header_lines.iter()
+            .map(|x| String::from_utf8_lossy(x).into_owned())
+            .collect::<Vec<String>>();
+        let header_warnings = check_hashdeep_log_header(&header_text);
+        let hash_columns = hash_columns_from_header(&header_text);
+
+        Ok(LogReader{reader, header_lines, header_warnings, hash_columns, pending_line, invalid_line_count: 0})
+    }
+
+    /// The log's header lines (without line endings)
+    pub fn header_lines(&self) -> &[Vec<u8>] {
+        &self.header_lines
+    }
+
+    /// Warnings about the log's header
+    pub fn header_warnings(&self) -> &[HashdeepLogHeaderWarning] {
+        &self.header_warnings
+    }
+
+    /// The hash columns of the log's entries, as listed in the header
+    pub fn hash_columns(&self) -> &[HashAlgorithm] {
+        &self.hash_columns
+    }
+
+    ///Returns a Vec of printable warning lines (or None, if no warnings or invalid lines exist)
+    ///
+    /// Invalid lines are counted as they are read: this should be called after all entries are read.
+    pub fn warning_report(&self) -> Option<Vec<String>> {
+        warning_report(&self.header_warnings, self.invalid_line_count)
+    }
+
+    /// Consumes the LogReader, returning an empty `LogFile` with its header information.
+    fn into_empty_log_file<T>(self) -> LogFile<T>
+        where T: Extend<LogEntry> + Default + IntoIterator
+    {
+        LogFile {
+            entries: T::default(),
+            header_warnings: self.header_warnings,
+            header_lines: self.header_lines,
+            hash_columns: self.hash_columns,
+            invalid_lines: vec![],
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = std::io::Result<Result<LogEntry, InvalidLine>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        let line = match self.pending_line.take() {
+            Some(line) => line,
+            None => match read_line(&mut self.reader) {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+
+        Some(Ok(
+            LogEntry::from_bytes(&line, &self.hash_columns).ok_or_else(|| {
+                self.invalid_line_count += 1;
+                InvalidLine(line)
+            })
+        ))
+    }
+}
+
+/// Reads a line (without its line ending), or returns None at the end of the source.
+fn read_line(reader: &mut impl BufRead) -> std::io::Result<Option<Vec<u8>>> {
+
+    let mut line = Vec::<u8>::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
         }
     }
+    Ok(Some(line))
 }
 
 /// Reads a hashdeep log: checks the header, then collects entries + parse failures
@@ -178,45 +374,86 @@ impl<T> LogFile<T>
 pub fn read_log_entries_from_file<T>(filename: &str) -> Result<LogFile<T>, ReadLogEntriesFromFileError>
     where T: Extend<LogEntry> + Default + IntoIterator
 {
-    let contents = read_to_string(filename)
-        .map_err(|e| ReadLogEntriesFromFileError::new(e, filename))?;
+    let mut log_reader = LogReader::open(filename)?;
 
     let mut entries = T::default();
-    let mut invalid_lines = Vec::<String>::new();
+    let mut invalid_lines = Vec::<Vec<u8>>::new();
 
-    let mut lines = contents.lines().peekable();
+    for item in &mut log_reader {
+        match item.map_err(|e| ReadLogEntriesFromFileError::new(e, filename))? {
+            Ok(log_entry) => entries.extend(std::iter::once(log_entry)),
+            Err(InvalidLine(line)) => invalid_lines.push(line),
+        }
+    }
 
-    //collect the header lines based on expected prefix symbols
-    let header_lines: Vec<String> = lines.peeking_take_while(|x: &&str| {
-        x.starts_with("%%%%") ||
-        x.starts_with("##")
-    }).map(|s| s.to_string() ).collect();
+    Ok(LogFile{entries, invalid_lines, ..log_reader.into_empty_log_file()})
+}
 
-    let header_warnings = check_hashdeep_log_header(&header_lines);
+fn open_writable_file(filename: &str) -> Result<BufWriter<File>, WriteToFileError>
+{
+    OpenOptions::new().write(true).create_new(true).open(filename)
+        .map(BufWriter::new)
+        .map_err(|e| WriteToFileError::new(e, filename))
+}
 
-    entries.extend(lines.filter_map(|line| {
+/// Writes a hashdeep log to a new file incrementally: header lines first, then one entry at a time.
+pub struct LogFileWriter {
+    file: BufWriter<File>,
+}
 
-        LogEntry::from_str(line).or_else( || {
-            invalid_lines.push(line.to_owned());
-            None
-        })
-    }));
+impl LogFileWriter {
 
+    /// Creates a new file (will not overwrite an existing file) and writes `header_lines` to it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file at `filename` already exists, or
+    /// if an error occurs while writing to the file.
+    pub fn create(filename: &str, header_lines: &[Vec<u8>]) -> Result<Self, WriteToFileError>
+    {
+        let mut file = open_writable_file(filename)?;
 
-    Ok(LogFile{entries, header_warnings, header_lines, invalid_lines})
+        for header_line in header_lines {
+            write_line_to_file(header_line, &mut file)?;
+        }
+
+        Ok(LogFileWriter{file})
+    }
+
+    /// Writes a log entry line.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while writing will be returned.
+    pub fn write_entry(&mut self, log_entry: &LogEntry) -> Result<(), WriteToFileError>
+    {
+        write_log_entry_to_file("", log_entry, &mut self.file)
+    }
+
+    /// Flushes buffered output to the file.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while writing will be returned.
+    pub fn finish(mut self) -> Result<(), WriteToFileError>
+    {
+        self.file.flush()?;
+        Ok(())
+    }
 }
 
-fn open_writable_file(filename: &str) -> Result<File, WriteToFileError>
+fn write_line_to_file(line: &[u8], file: &mut impl Write) -> Result<(), WriteToFileError>
 {
-    OpenOptions::new().write(true).create_new(true).open(filename)
-        .map_err(|e| WriteToFileError::new(e, filename))
+    file.write_all(line)?;
+    file.write_all(b"\n")?;
+    Ok(())
 }
 
-fn write_log_entry_to_file(label: &str, log_entry_str: &str, file: &mut File) -> Result<(), WriteToFileError>
+fn write_log_entry_to_file(label: &str, log_entry: &LogEntry, file: &mut impl Write) -> Result<(), WriteToFileError>
 {
-    let line = format!("{label}{log_entry_str}\n");
-
-    file.write_all(line.as_bytes())?;
+    file.write_all(label.as_bytes())?;
+    log_entry.write_to(file)?;
+    file.write_all(b"\n")?;
     Ok(())
 }
 
@@ -230,19 +467,19 @@ fn write_log_entry_to_file(label: &str, log_entry_str: &str, file: &mut File) ->
 /// Will return an error if the file at `filename` already exists, or
 /// if an error occurs while writing to the file.
 pub fn write_log_file_to_file<T>(log_file: LogFile<T>, filename: &str) -> Result<(), WriteToFileError>
-    where T: Extend<LogEntry> + Default + IntoIterator, <T as IntoIterator>::Item : ToString
+    where T: Extend<LogEntry> + Default + IntoIterator, <T as IntoIterator>::Item : Borrow<LogEntry>
 {
     let mut file = open_writable_file(filename)?;
 
     for header_line in log_file.header_lines {
-        let header_line = format!("{header_line}\n");
-        file.write_all(header_line.as_bytes())?;
+        write_line_to_file(&header_line, &mut file)?;
     }
 
     for log_entry in log_file.entries {
-        write_log_entry_to_file("", &log_entry.to_string(), &mut file)?;
+        write_log_entry_to_file("", log_entry.borrow(), &mut file)?;
     };
 
+    file.flush()?;
     Ok(())
 }
 
@@ -253,14 +490,34 @@ pub fn write_log_file_to_file<T>(log_file: LogFile<T>, filename: &str) -> Result
 /// Will return an error if the file at `filename` already exists, or
 /// if an error occurs while writing to the file.
 pub fn write_log_entries_to_file<T>(log_entries: T, filename: &str) -> Result<(), WriteToFileError>
-    where T: IntoIterator, <T as IntoIterator>::Item : ToString
+    where T: IntoIterator, <T as IntoIterator>::Item : Borrow<LogEntry>
 {
     let mut file = open_writable_file(filename)?;
 
     for log_entry in log_entries {
-        write_log_entry_to_file("", &log_entry.to_string(), &mut file)?;
+        write_log_entry_to_file("", log_entry.borrow(), &mut file)?;
+    };
+
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes lines to a new file (will not overwrite an existing file).
+///
+/// # Errors
+///
+/// Will return an error if the file at `filename` already exists, or
+/// if an error occurs while writing to the file.
+pub fn write_lines_to_file<T>(lines: T, filename: &str) -> Result<(), WriteToFileError>
+    where T: IntoIterator, <T as IntoIterator>::Item : AsRef<[u8]>
+{
+    let mut file = open_writable_file(filename)?;
+
+    for line in lines {
+        write_line_to_file(line.as_ref(), &mut file)?;
     };
 
+    file.flush()?;
     Ok(())
 }
 
@@ -275,11 +532,12 @@ pub fn write_match_pairs_to_file(match_pairs: &[MatchPair], filename: &str) -> R
     let mut file = open_writable_file(filename)?;
 
     for match_pair in match_pairs {
-        write_log_entry_to_file("file1: ", &match_pair.from_file1.to_string(), &mut file)?;
-        write_log_entry_to_file("file2: ", &match_pair.from_file2.to_string(), &mut file)?;
+        write_log_entry_to_file("file1: ", match_pair.from_file1, &mut file)?;
+        write_log_entry_to_file("file2: ", match_pair.from_file2, &mut file)?;
         file.write_all(b"\n")?;
     };
 
+    file.flush()?;
     Ok(())
 }
 
@@ -295,10 +553,10 @@ pub fn write_match_groups_to_file(match_groups: &[MatchGroup], filename: &str) -
 
     for match_group in match_groups {
 
-        fn write_entries(entries: &SomeVec<&LogEntry>, label: &str, file: &mut File) -> Result<(), WriteToFileError>
+        fn write_entries(entries: &SomeVec<&LogEntry>, label: &str, file: &mut impl Write) -> Result<(), WriteToFileError>
         {
             for &log_entry in entries.inner_ref() {
-                write_log_entry_to_file(label, &log_entry.to_string(), file)?;
+                write_log_entry_to_file(label, log_entry, file)?;
             };
             Ok(())
         }
@@ -308,6 +566,35 @@ pub fn write_match_groups_to_file(match_groups: &[MatchGroup], filename: &str) -
         file.write_all(b"\n")?;
     };
 
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes directory moves to a new file (will not overwrite an existing file):
+/// each move's description, followed by its exceptions.
+///
+/// # Errors
+///
+/// Will return an error if the file at `filename` already exists, or
+/// if an error occurs while writing to the file.
+pub fn write_directory_moves_to_file(directory_moves: &[DirectoryMove], filename: &str) -> Result<(), WriteToFileError>
+{
+    let mut file = open_writable_file(filename)?;
+
+    for directory_move in directory_moves {
+        file.write_all(directory_move.description().as_bytes())?;
+        file.write_all(b"\n")?;
+
+        for &log_entry in &directory_move.exceptions_file1 {
+            write_log_entry_to_file("exception: file1: ", log_entry, &mut file)?;
+        };
+        for &log_entry in &directory_move.exceptions_file2 {
+            write_log_entry_to_file("exception: file2: ", log_entry, &mut file)?;
+        };
+        file.write_all(b"\n")?;
+    };
+
+    file.flush()?;
     Ok(())
 }
 
@@ -324,12 +611,13 @@ pub fn write_single_file_match_groups_to_file(single_file_match_groups: &[Single
 
     for single_file_match_group in single_file_match_groups {
 
-        for log_entry in single_file_match_group.log_entries.inner_ref() {
-            write_log_entry_to_file("", &log_entry.to_string(), &mut file)?;
+        for &log_entry in single_file_match_group.log_entries.inner_ref() {
+            write_log_entry_to_file("", log_entry, &mut file)?;
         };
         file.write_all(b"\n")?;
     };
 
+    file.flush()?;
     Ok(())
 }
 
@@ -340,12 +628,41 @@ mod test
     use predicates::prelude::*;
     use test_case::test_case;
 
+    #[test]
+    fn format_byte_count_test() {
+        assert_eq!(format_byte_count(0), "0 bytes");
+        assert_eq!(format_byte_count(1023), "1023 bytes");
+        assert_eq!(format_byte_count(1024), "1.0 KiB");
+        assert_eq!(format_byte_count(1536), "1.5 KiB");
+        assert_eq!(format_byte_count(100 << 20), "100.0 MiB");
+        assert_eq!(format_byte_count(3 << 40), "3.0 TiB");
+        assert_eq!(format_byte_count(u64::MAX), "16384.0 PiB");
+    }
+
+    #[test]
+    fn parse_byte_count_test() {
+        assert_eq!(parse_byte_count("0"), Ok(0));
+        assert_eq!(parse_byte_count("500000"), Ok(500000));
+        assert_eq!(parse_byte_count("512K"), Ok(512 << 10));
+        assert_eq!(parse_byte_count("100m"), Ok(100 << 20));
+        assert_eq!(parse_byte_count("2G"), Ok(2 << 30));
+        assert_eq!(parse_byte_count("1T"), Ok(1 << 40));
+        assert!(parse_byte_count("").is_err());
+        assert!(parse_byte_count("K").is_err());
+        assert!(parse_byte_count("1.5G").is_err());
+        assert!(parse_byte_count("-1").is_err());
+        assert!(parse_byte_count("100KB").is_err());
+        assert!(parse_byte_count("99999999999T").is_err());
+    }
+
     #[test_case("tests/test1.txt")]
     #[test_case("tests/sort_files/test1_header_not_found.txt")]
     #[test_case("tests/sort_files/test1_multiple_warnings.txt")]
     #[test_case("tests/sort_files/test1_unexpected_header_line_count.txt")]
     #[test_case("tests/sort_files/test1_unexpected_version_string.txt")]
     #[test_case("tests/sort_files/test1_untested_log_format.txt")]
+    #[test_case("tests/sort_files/test1_sha1_tiger_whirlpool.txt")]
+    #[test_case("tests/sort_files/test1_non_utf8_filenames.txt")]
     fn write_log_file_to_file_round_trip(filename: &str) {
         let temp_dir = tempfile::tempdir().unwrap();
         let temp_file = temp_dir.path().join("temp_file");
@@ -358,6 +675,44 @@ mod test
         assert!(p.eval(temp_file.as_path()));
     }
 
+    #[test]
+    fn log_reader_test() {
+        let log = indoc::indoc!("
+            %%%% HASHDEEP-1.0
+            %%%% size,md5,filename
+            ## Invoked from: /home/user
+            ## $ hashdeep -c md5 -r dir
+            ##\x20
+            1,d41d8cd98f00b204e9800998ecf8427e,dir/file1
+            not a log entry
+            2,d41d8cd98f00b204e9800998ecf8427e,dir/file2");
+
+        let mut log_reader = LogReader::new(log.as_bytes()).unwrap();
+
+        assert_eq!(log_reader.header_lines().len(), 5);
+        assert_eq!(log_reader.header_warnings(), []);
+        assert_eq!(log_reader.hash_columns, [HashAlgorithm::Md5]);
+
+        let items = (&mut log_reader).collect::<std::io::Result<Vec<_>>>().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().filename, b"dir/file1");
+        assert_eq!(items[1], Err(InvalidLine(b"not a log entry".to_vec())));
+        assert_eq!(items[2].as_ref().unwrap().filename, b"dir/file2");
+
+        assert_eq!(log_reader.warning_report(), Some(vec!["1 invalid log entry detected".to_string()]));
+    }
+
+    #[test]
+    fn log_reader_no_header_test() {
+        let log = "1,d41d8cd98f00b204e9800998ecf8427e,e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855,file\n";
+
+        let mut log_reader = LogReader::new(log.as_bytes()).unwrap();
+
+        assert_eq!(log_reader.header_warnings(), [HashdeepLogHeaderWarning::HeaderNotFound]);
+        assert_eq!(log_reader.next().unwrap().unwrap().unwrap().filename, b"file");
+        assert!(log_reader.next().is_none());
+    }
+
     #[test]
     fn check_hashdeep_log_header_test() {
 
@@ -384,6 +739,23 @@ mod test
             assert_eq!(warnings, expected);
         }
 
+        //success with other hash columns
+        for format_line in ["%%%% size,md5,filename", "%%%% size,sha1,tiger,whirlpool,filename"] {
+            let header_lines = [
+                "%%%% HASHDEEP-1.0",
+                format_line,
+                "## Invoked from: /home/user",
+                "## $ hashdeep -c sha1,tiger,whirlpool -lr hashdeepComp/",
+                "## ",
+            ];
+
+            let expected = [];
+
+            let warnings = check_hashdeep_log_header(&to_vec_string(&header_lines));
+
+            assert_eq!(warnings, expected);
+        }
+
         //success with sort message in header
         {
             let header_lines = [
@@ -430,13 +802,13 @@ mod test
         {
             let header_lines = [
                 "%%%% HASHDEEP-1.0",
-                "%%%% size,md5,filename",
+                "%%%% size,md5,fake_hash,filename",
                 "## Invoked from: /home/user",
                 "## $ hashdeep -lr hashdeepComp/",
                 "## ",
             ];
 
-            let expected = [UntestedLogFormat("%%%% size,md5,filename".into())];
+            let expected = [UntestedLogFormat("%%%% size,md5,fake_hash,filename".into())];
 
             let warnings = check_hashdeep_log_header(&to_vec_string(&header_lines));
 
@@ -461,12 +833,12 @@ mod test
         {
             let header_lines = [
                 "%%%% HASHDEEP-3.0",
-                "%%%% size,sha256,filename",
+                "%%%% size,sha256,fake_hash,filename",
             ];
 
             let expected = [
                 UnexpectedVersionString("%%%% HASHDEEP-3.0".into()),
-                UntestedLogFormat("%%%% size,sha256,filename".into()),
+                UntestedLogFormat("%%%% size,sha256,fake_hash,filename".into()),
                 UnexpectedHeaderLineCount(2),
             ];
 
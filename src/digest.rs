@@ -0,0 +1,276 @@
+//! Hash algorithms and digest values, as recorded in hashdeep logs
+
+use std::fmt;
+
+/// A fixed-width hash digest value.
+///
+/// Digests are parsed from (and displayed as) lowercase hexadecimal, which is what hashdeep writes.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct HexDigest<const N: usize>([u8; N]);
+
+impl<const N: usize> HexDigest<N> {
+
+    /// Creates a HexDigest from its raw bytes.
+    pub fn new(bytes: [u8; N]) -> HexDigest<N> {
+        HexDigest(bytes)
+    }
+
+    /// Creates a HexDigest from exactly `2 * N` lowercase hex digits, or returns None on failure.
+    pub fn from_hex(s: &str) -> Option<HexDigest<N>> {
+
+        fn nibble(c: u8) -> Option<u8> {
+            match c {
+                b'0'..=b'9' => Some(c - b'0'),
+                b'a'..=b'f' => Some(c - b'a' + 10),
+                _ => None,
+            }
+        }
+
+        let s = s.as_bytes();
+        if s.len() != N * 2 {return None;}
+
+        let mut bytes = [0u8; N];
+        for (byte, pair) in bytes.iter_mut().zip(s.chunks_exact(2)) {
+            *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+        }
+
+        Some(HexDigest(bytes))
+    }
+}
+
+impl<const N: usize> fmt::Display for HexDigest<N> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// A hash algorithm that hashdeep can record in a log column.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Tiger,
+    Whirlpool,
+}
+
+impl HashAlgorithm {
+
+    /// The hash columns hashdeep writes by default (`size,md5,sha256,filename`)
+    pub const DEFAULT_COLUMNS: [HashAlgorithm; 2] = [HashAlgorithm::Md5, HashAlgorithm::Sha256];
+
+    /// Gets the algorithm's name, as used in hashdeep log headers and hashdeep's `-c` option
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Tiger => "tiger",
+            HashAlgorithm::Whirlpool => "whirlpool",
+        }
+    }
+
+    /// Gets an algorithm from its hashdeep name, or returns None if it isn't recognized.
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        match name {
+            "md5" => Some(HashAlgorithm::Md5),
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "tiger" => Some(HashAlgorithm::Tiger),
+            "whirlpool" => Some(HashAlgorithm::Whirlpool),
+            _ => None,
+        }
+    }
+
+    /// Reads the hash columns from a hashdeep log format line (the 2nd header line),
+    /// e.g. `%%%% size,md5,sha256,filename`.
+    ///
+    /// Returns None if the line isn't in that form, or if any column is unrecognized or repeated.
+    pub fn columns_from_format_line(line: &str) -> Option<Vec<HashAlgorithm>> {
+
+        let names = line.strip_prefix("%%%% size,")?.strip_suffix(",filename")?;
+        HashAlgorithm::list_from_names(names).ok()
+    }
+
+    /// Reads a comma-separated list of algorithm names (as used by hashdeep's `-c` option), e.g. `md5,sha256`.
+    ///
+    /// # Errors
+    ///
+    /// An error message will be returned if the list is empty, or if any name is unrecognized or repeated.
+    pub fn list_from_names(names: &str) -> Result<Vec<HashAlgorithm>, String> {
+
+        let mut algorithms = Vec::<HashAlgorithm>::new();
+        for name in names.split(',') {
+            let algorithm = HashAlgorithm::from_name(name).ok_or_else(|| match name {
+                "" => "expected a comma-separated list of algorithms".to_string(),
+                _ => format!("unknown algorithm \"{name}\" (expected any of: md5, sha1, sha256, tiger, whirlpool)"),
+            })?;
+            if algorithms.contains(&algorithm) {
+                return Err(format!("algorithm \"{name}\" is repeated"));
+            }
+            algorithms.push(algorithm);
+        }
+
+        Ok(algorithms)
+    }
+
+    /// Creates a hashdeep log format line (the 2nd header line) for the specified hash columns.
+    pub fn format_line(columns: &[HashAlgorithm]) -> String {
+        format!("%%%% size,{},filename", HashAlgorithm::names(columns))
+    }
+
+    /// Gets a printable comma-separated list of algorithm names.
+    pub fn names(algorithms: &[HashAlgorithm]) -> String {
+        algorithms.iter().map(HashAlgorithm::name).collect::<Vec<&str>>().join(",")
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A digest value from one of the supported hash algorithms.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Digest {
+    Md5(HexDigest<16>),
+    Sha1(HexDigest<20>),
+    Sha256(HexDigest<32>),
+    Tiger(HexDigest<24>),
+    Whirlpool(HexDigest<64>),
+}
+
+impl Digest {
+
+    /// Creates a Digest of `algorithm` from its lowercase hex representation,
+    /// or returns None if it isn't valid for that algorithm.
+    pub fn from_hex(algorithm: HashAlgorithm, s: &str) -> Option<Digest> {
+        match algorithm {
+            HashAlgorithm::Md5 => HexDigest::from_hex(s).map(Digest::Md5),
+            HashAlgorithm::Sha1 => HexDigest::from_hex(s).map(Digest::Sha1),
+            HashAlgorithm::Sha256 => HexDigest::from_hex(s).map(Digest::Sha256),
+            HashAlgorithm::Tiger => HexDigest::from_hex(s).map(Digest::Tiger),
+            HashAlgorithm::Whirlpool => HexDigest::from_hex(s).map(Digest::Whirlpool),
+        }
+    }
+
+    /// Creates a Digest of `algorithm` from its raw bytes,
+    /// or returns None if the length isn't valid for that algorithm.
+    pub fn from_bytes(algorithm: HashAlgorithm, bytes: &[u8]) -> Option<Digest> {
+        match algorithm {
+            HashAlgorithm::Md5 => bytes.try_into().ok().map(|x| Digest::Md5(HexDigest(x))),
+            HashAlgorithm::Sha1 => bytes.try_into().ok().map(|x| Digest::Sha1(HexDigest(x))),
+            HashAlgorithm::Sha256 => bytes.try_into().ok().map(|x| Digest::Sha256(HexDigest(x))),
+            HashAlgorithm::Tiger => bytes.try_into().ok().map(|x| Digest::Tiger(HexDigest(x))),
+            HashAlgorithm::Whirlpool => bytes.try_into().ok().map(|x| Digest::Whirlpool(HexDigest(x))),
+        }
+    }
+
+    /// Gets the algorithm that produced this digest.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            Digest::Md5(_) => HashAlgorithm::Md5,
+            Digest::Sha1(_) => HashAlgorithm::Sha1,
+            Digest::Sha256(_) => HashAlgorithm::Sha256,
+            Digest::Tiger(_) => HashAlgorithm::Tiger,
+            Digest::Whirlpool(_) => HashAlgorithm::Whirlpool,
+        }
+    }
+}
+
+impl fmt::Display for Digest {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Digest::Md5(x) => x.fmt(f),
+            Digest::Sha1(x) => x.fmt(f),
+            Digest::Sha256(x) => x.fmt(f),
+            Digest::Tiger(x) => x.fmt(f),
+            Digest::Whirlpool(x) => x.fmt(f),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_digest_test() {
+        assert_eq!(HexDigest::<2>::from_hex("00ff").unwrap().to_string(), "00ff");
+        assert_eq!(HexDigest::<2>::from_hex("a1b2"), Some(HexDigest([0xa1, 0xb2])));
+
+        assert_eq!(HexDigest::<2>::from_hex(""), None);
+        assert_eq!(HexDigest::<2>::from_hex("a1b"), None);
+        assert_eq!(HexDigest::<2>::from_hex("a1b2c"), None);
+        assert_eq!(HexDigest::<2>::from_hex("A1B2"), None);
+        assert_eq!(HexDigest::<2>::from_hex("g1b2"), None);
+        assert_eq!(HexDigest::<2>::from_hex("+1b2"), None);
+    }
+
+    #[test]
+    fn columns_from_format_line_test() {
+        use HashAlgorithm::*;
+
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,md5,sha256,filename"), Some(vec![Md5, Sha256]));
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,md5,filename"), Some(vec![Md5]));
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,sha1,tiger,whirlpool,filename"), Some(vec![Sha1, Tiger, Whirlpool]));
+
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,filename"), None);
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,md5,fake_hash,filename"), None);
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,md5,md5,filename"), None);
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% md5,sha256,filename"), None);
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% size,md5,sha256"), None);
+        assert_eq!(HashAlgorithm::columns_from_format_line("%%%% fake format string"), None);
+    }
+
+    #[test]
+    fn list_from_names_test() {
+        use HashAlgorithm::*;
+
+        assert_eq!(HashAlgorithm::list_from_names("sha256"), Ok(vec![Sha256]));
+        assert_eq!(HashAlgorithm::list_from_names("whirlpool,md5"), Ok(vec![Whirlpool, Md5]));
+
+        assert!(HashAlgorithm::list_from_names("").is_err());
+        assert!(HashAlgorithm::list_from_names("md5,").is_err());
+        assert!(HashAlgorithm::list_from_names("md5,sha512").is_err());
+        assert!(HashAlgorithm::list_from_names("md5,md5").is_err());
+        assert!(HashAlgorithm::list_from_names("MD5").is_err());
+    }
+
+    #[test]
+    fn format_line_test() {
+        use HashAlgorithm::*;
+
+        assert_eq!(HashAlgorithm::format_line(&HashAlgorithm::DEFAULT_COLUMNS), "%%%% size,md5,sha256,filename");
+
+        for columns in [vec![Md5], vec![Sha1, Tiger, Whirlpool], vec![Sha256, Md5]] {
+            let line = HashAlgorithm::format_line(&columns);
+            assert_eq!(HashAlgorithm::columns_from_format_line(&line), Some(columns));
+        }
+    }
+
+    #[test]
+    fn digest_from_hex_test() {
+        let sha1 = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        let digest = Digest::from_hex(HashAlgorithm::Sha1, sha1).unwrap();
+        assert_eq!(digest.algorithm(), HashAlgorithm::Sha1);
+        assert_eq!(digest.to_string(), sha1);
+
+        assert_eq!(Digest::from_hex(HashAlgorithm::Md5, sha1), None);
+        assert_eq!(Digest::from_hex(HashAlgorithm::Sha256, sha1), None);
+    }
+
+    #[test]
+    fn digest_from_bytes_test() {
+        let digest = Digest::from_bytes(HashAlgorithm::Md5, &[0xab; 16]).unwrap();
+        assert_eq!(digest.to_string(), "ab".repeat(16));
+
+        assert_eq!(Digest::from_bytes(HashAlgorithm::Md5, &[0xab; 20]), None);
+        assert_eq!(Digest::from_bytes(HashAlgorithm::Whirlpool, &[]), None);
+    }
+}
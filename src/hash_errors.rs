@@ -0,0 +1,242 @@
+//! Reading the error files written by the `hash` subcommand
+
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The type of failure reported for a path in a hash error file
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum HashErrorKind {
+    PermissionDenied,
+    NotFound,
+    Io,
+    UnknownFileType,
+    /// Any other error (with its message)
+    Other(String),
+}
+
+impl HashErrorKind {
+
+    /// Classifies an error message (as written by hashdeep, or by the native engine).
+    pub fn from_message(message: &str) -> Self {
+
+        let lowercase = message.to_lowercase();
+
+        if lowercase.contains("permission denied") {
+            HashErrorKind::PermissionDenied
+        }
+        else if lowercase.contains("no such file or directory") {
+            HashErrorKind::NotFound
+        }
+        else if lowercase.contains("input/output error") || lowercase.contains("i/o error") {
+            HashErrorKind::Io
+        }
+        else if lowercase.contains("unknown file type") {
+            HashErrorKind::UnknownFileType
+        }
+        else {
+            HashErrorKind::Other(message.to_string())
+        }
+    }
+}
+
+impl From<&std::io::Error> for HashErrorKind {
+
+    fn from(e: &std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => HashErrorKind::PermissionDenied,
+            std::io::ErrorKind::NotFound => HashErrorKind::NotFound,
+            _ => HashErrorKind::from_message(&e.to_string()),
+        }
+    }
+}
+
+impl Display for HashErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+
+        use HashErrorKind::*;
+
+        match self {
+            PermissionDenied => write!(f, "permission denied"),
+            NotFound => write!(f, "not found"),
+            Io => write!(f, "I/O error"),
+            UnknownFileType => write!(f, "unknown file type"),
+            Other(_) => write!(f, "other error"),
+        }
+    }
+}
+
+/// A failure reported for a single path
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct HashError {
+    /// The path's exact bytes
+    pub path: Vec<u8>,
+    pub kind: HashErrorKind,
+}
+
+impl HashError {
+
+    /// Reads an error file line in the form `path: message`, or returns None if it isn't in that form.
+    ///
+    /// The path is assumed to end at the last ": " in the line, since messages don't usually contain one.
+    pub fn from_line(line: &[u8]) -> Option<HashError> {
+
+        let separator = line.windows(2).rposition(|x| x == b": ")?;
+        let (path, message) = (&line[..separator], &line[separator + 2..]);
+
+        Some(HashError {
+            path: path.to_vec(),
+            kind: HashErrorKind::from_message(&String::from_utf8_lossy(message)),
+        })
+    }
+}
+
+/// The failures reported in a hash error file, with at most 1 failure per path
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct HashErrorSummary {
+    /// The path of the error file
+    pub error_file_path: String,
+    /// Failures, in the order they were first reported
+    pub errors: Vec<HashError>,
+    /// Lines that couldn't be read as `path: message`
+    pub unrecognized_lines: Vec<Vec<u8>>,
+    /// The paths in `errors`
+    error_paths: HashSet<Vec<u8>>,
+}
+
+impl HashErrorSummary {
+
+    /// Creates an empty summary for the error file at `error_file_path`.
+    pub fn new(error_file_path: String) -> Self {
+        HashErrorSummary{error_file_path, ..Default::default()}
+    }
+
+    /// Reads and summarizes an error file.
+    ///
+    /// When multiple lines refer to the same path (e.g.: hashdeep follows "No such file or directory"
+    /// with "unknown file type"), the first line's failure is used.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while reading the file will be returned.
+    pub fn from_file(error_file_path: impl AsRef<Path>) -> std::io::Result<Self> {
+
+        let error_file_path = error_file_path.as_ref();
+        let mut summary = HashErrorSummary::new(error_file_path.display().to_string());
+
+        let reader = BufReader::new(std::fs::File::open(error_file_path)?);
+        for line in reader.split(b'\n') {
+            let line = line?;
+            if line.is_empty() {continue;}
+
+            match HashError::from_line(&line) {
+                Some(error) => summary.push(error),
+                None => summary.unrecognized_lines.push(line),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Adds a failure, unless its path already has one.
+    pub fn push(&mut self, error: HashError) {
+        if self.error_paths.insert(error.path.clone()) {
+            self.errors.push(error);
+        }
+    }
+
+    ///Returns a Vec of printable summary lines (or None, if no failures were reported)
+    pub fn summary_lines(&self) -> Option<Vec<String>> {
+
+        let reported_count = self.errors.len() + self.unrecognized_lines.len();
+        if reported_count == 0 {
+            return None;
+        }
+
+        let mut lines = vec![match self.errors.len() {
+            0 => format!("Warning: errors were reported (see {}):", self.error_file_path),
+            1 => format!("Warning: errors were reported for 1 path (see {}):", self.error_file_path),
+            x => format!("Warning: errors were reported for {x} paths (see {}):", self.error_file_path),
+        }];
+
+        //count failures by their Display text, in order of first appearance
+        let mut counts = Vec::<(String, usize)>::new();
+        for error in &self.errors {
+            let label = error.kind.to_string();
+            match counts.iter_mut().find(|(x, _)| *x == label) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((label, 1)),
+            }
+        }
+        lines.extend(counts.into_iter().map(|(label, count)| format!("  {count} {label}")));
+
+        match self.unrecognized_lines.len() {
+            0 => {},
+            1 => lines.push("  1 unrecognized error line".to_string()),
+            x => lines.push(format!("  {x} unrecognized error lines")),
+        }
+
+        Some(lines)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_line_test() {
+        use HashErrorKind::*;
+
+        let error = HashError::from_line(b"dir/a file: Permission denied").unwrap();
+        assert_eq!(error.path, b"dir/a file");
+        assert_eq!(error.kind, PermissionDenied);
+
+        let error = HashError::from_line(b"dir/a: b.txt: Input/output error").unwrap();
+        assert_eq!(error.path, b"dir/a: b.txt");
+        assert_eq!(error.kind, Io);
+
+        assert_eq!(HashError::from_line(b"does_not_exist/: No such file or directory").unwrap().kind, NotFound);
+        assert_eq!(HashError::from_line(b"does_not_exist/: unknown file type").unwrap().kind, UnknownFileType);
+        assert_eq!(HashError::from_line(b"x: No such file or directory (os error 2)").unwrap().kind, NotFound);
+        assert_eq!(HashError::from_line(b"x: Something else").unwrap().kind, Other("Something else".to_string()));
+
+        assert_eq!(HashError::from_line(b"no separator"), None);
+    }
+
+    #[test]
+    fn from_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog.errors");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        std::fs::write(&temp_file, concat!(
+            "does_not_exist/: No such file or directory\n",
+            "does_not_exist/: unknown file type\n",
+            "dir/1: Permission denied\n",
+            "dir/2: Permission denied\n",
+            "dir/3: Input/output error\n",
+            "garbage\n",
+        )).unwrap();
+
+        let summary = HashErrorSummary::from_file(temp_file_path_str).unwrap();
+        assert_eq!(summary.errors.len(), 4);
+        assert_eq!(summary.errors[0].kind, HashErrorKind::NotFound);
+        assert_eq!(summary.unrecognized_lines, [b"garbage".to_vec()]);
+
+        assert_eq!(summary.summary_lines().unwrap(), [
+            format!("Warning: errors were reported for 4 paths (see {temp_file_path_str}):"),
+            "  1 not found".to_string(),
+            "  2 permission denied".to_string(),
+            "  1 I/O error".to_string(),
+            "  1 unrecognized error line".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn summary_lines_empty_test() {
+        assert_eq!(HashErrorSummary::default().summary_lines(), None);
+    }
+}
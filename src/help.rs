@@ -22,15 +22,101 @@ pub fn help_hash_string() -> String {
                   > path/to/output_log.txt \\
                   2> path/to/output_log.txt.errors
 
+            Options change the hashdeep command line as follows:
+                --algorithms LIST       adds   -c LIST   (e.g.: -c md5,sha1)
+                --jobs N                adds   -j N
+                --file-types TYPES      uses   -o TYPES  instead of -o f
+                --hashdeep-binary BIN   runs   BIN       instead of hashdeep
+            e.g.: `hash --algorithms sha1 --jobs 4 --file-types fl target_dir log.txt`
+            is equivalent to
+                hashdeep -l -r -c sha1 -j 4 -o fl target_dir \\
+                  > log.txt \\
+                  2> log.txt.errors
+
+            With --relative, hashdeep is run from inside the target directory (as
+                cd path/to/target_dir && hashdeep -l -r -o f . > ...
+            ), so logged paths are relative to it (e.g.: ./dir/file). The log header
+            records the absolute target directory and the host name instead of the
+            invocation directory. Logs of the same files can then be compared with
+            `part` directly, even if they were mounted at different paths.
+
+            --file-types accepts any of hashdeep's -o letters: f (regular files),
+            b (block devices), c (character devices), p (named pipes), l (symbolic
+            links), s (sockets), d (Solaris doors), e (Windows PE executables).
+            --hashdeep-binary must name a hashdeep binary (e.g.: hashdeep64): other
+            programs in its family, like md5deep, write logs in a different format.
+
             Note that if the output file or the error file already exists, the command
             will be aborted (hashdeep-compare will not overwrite existing files).
+
+            If any errors are written to the error file (e.g.: files that can't be read),
+            a summary of them will be printed. If hashdeep exits with a failure status,
+            the command will fail.
+
+            While hashing, output is written to temporary files (with a \".partial\"
+            suffix): they are renamed to the output paths only when hashing succeeds,
+            and removed if it fails or is interrupted (e.g.: with Ctrl-C). If hashdeep
+            fails after reporting errors, the error file is kept as
+            path/to/output_log.txt.errors.failed, so they can be read (this doesn't
+            prevent the command from being run again).
+
+            With `--engine native`, the log is generated by hashdeep-compare's built-in
+            hashing engine instead, which doesn't require hashdeep to be installed.
+            It hashes the same files (with the same header and entry format), and
+            writes read errors to the error file.
+            Files are hashed in parallel (see --jobs and --max-reads), but log entries
+            are always written in the same order: sorted by file path, like `sort`.
+            It supports --algorithms, but only hashes regular files (like -o f).
+
+            The native engine can also update a log incrementally: with --save-mtimes,
+            it writes each file's modification time to path/to/output_log.txt.mtimes.
+            With --previous path/to/previous_log.txt (which must have been written with
+            --save-mtimes), files with the same size and modification time as before
+            are not read again: their previous log entries are reused. The previous
+            log must log paths the same way (the same target path, or --relative).
+            The output is still a plain hashdeep log.
+        "
+    )
+}
+
+/// Gets the hash-archive function's `clap` "long_about" string
+pub fn long_about_hash_archive_string() -> String {
+    formatdoc!("
+        Hashes the members of a tar (optionally gzip-compressed) or zip archive,
+        without extracting it, and generates a log file compatible with
+        hashdeep-compare."
+    )
+}
+
+/// Gets the hash-archive function help string
+pub fn help_hash_archive_string() -> String {
+
+    formatdoc!("
+        Notes:
+            The archive format is identified from the file's contents, not its name.
+            (Tar files must be in POSIX or GNU format, as written by modern tar tools.)
+
+            The log has an entry for each regular file in the archive (as `hash` logs
+            only regular files), with the member's path as its file path: the log
+            can then be compared to a directory's log with `part`. Hard links in tar
+            archives are logged with the contents of the files they link to.
+
+            With --relative, member paths are logged as ./path (removing any leading
+            ./ or / from the stored path), to match the logs of `hash --relative`
+            (e.g.: for an archive made with `tar -C path/to/target_dir -cf archive.tar .`,
+            compare to the log of `hash --relative path/to/target_dir`).
+
+            Entries are sorted by file path, as by `sort`.
+
+            Note that if the output file already exists, the command will be aborted
+            before hashing starts (hashdeep-compare will not overwrite existing files).
         "
     )
 }
 
 /// Gets the sort function's `clap` "long_about" string
 pub fn long_about_sort_string() -> String {
-    "Sorts the entries in a hashdeep log by file path.".to_string()
+    "Sorts the entries in a hashdeep log by file path (or by another key).".to_string()
 }
 
 /// Gets the sort function help string
@@ -43,6 +129,21 @@ pub fn help_sort_string() -> String {
             hashdeep logs in a text-diff tool, which may be the easiest way to compare
             logs with uncomplicated differences.
 
+            Entries are sorted by file path by default. Other keys:
+                hashes    entries with the same content are grouped together
+                size      largest files first
+                path      path components are compared 1 at a time, so a directory's
+                          contents are kept together (a/b sorts before a.b/c)
+                natural   runs of digits are compared as numbers (file2 sorts
+                          before file10)
+            --reverse reverses the key's order. Entries with equal keys are sorted
+            by file path (not reversed), then by hashes, so the output is the same
+            regardless of the input's order.
+
+            Logs that don't fit in --memory-limit (by default, 1G: an estimate of the
+            entries' memory use) are sorted in parts, which are written to temporary
+            files in --temp-dir and then merged. The output is the same either way.
+
             Note that if the output file already exists, the command will be aborted
             (hashdeep-compare will not overwrite existing files).
         "
@@ -67,9 +168,17 @@ pub fn help_root_string() -> String {
             unintended parent directories on all of its filepaths because of its invocation
             directory.
 
-            Warning: The prefix is applied as simple text, without any rules related to paths.
-            If the prefix \"test\" were used on the filepath \"testdir/file.txt\",
-            the resulting filepath would be \"dir/file.txt\".
+            The prefix matches whole path components: \"hashdeepComp/\", \"./hashdeepComp\"
+            and \"hashdeepComp//\" all remove the same prefix, and match both
+            \"hashdeepComp/file.txt\" and \"./hashdeepComp//file.txt\" (giving \"file.txt\").
+            A prefix that ends inside a path component is rejected with an error:
+            if the prefix \"test\" were used on the filepath \"testdir/file.txt\",
+            the command would be aborted, rather than omitting the entry. A prefix that
+            ends with a separator (e.g.: \"test/\") can't end inside a path component:
+            \"testdir/file.txt\" would just be omitted.
+
+            --raw applies the prefix as simple text instead, without any rules related
+            to paths: the prefix \"test\" would change \"testdir/file.txt\" to \"dir/file.txt\".
             Splitting the text of a path component like this probably isn't what you want,
             but there may be some clever uses for it.
 
@@ -79,6 +188,43 @@ pub fn help_root_string() -> String {
     )
 }
 
+/// Gets the rewrite function's `clap` "long_about" string
+pub fn long_about_rewrite_string() -> String {
+    formatdoc!("
+        Rewrites the filepaths of a hashdeep log with ordered rules.
+        Each entry is rewritten by the first rule that matches its filepath."
+    )
+}
+
+/// Gets the rewrite function help string
+pub fn help_rewrite_string() -> String {
+
+    formatdoc!("
+        Rules:
+            add PREFIX                    adds PREFIX to every filepath (a leading
+                                          \"./\" or \"/\" is removed from the filepath first)
+            replace FROM TO               replaces the prefix FROM with TO; FROM matches
+                                          whole path components, as for `root`
+            regex PATTERN REPLACEMENT     replaces the first match of a regular expression;
+                                          REPLACEMENT may refer to capture groups
+                                          ($1, ${{name}})
+
+            e.g.: hashdeep-compare rewrite in.txt out.txt replace /mnt/old/ /srv/new/
+
+            --rules-file reads rules from a file, 1 rule per line, with fields
+            separated by tabs (blank lines and lines starting with # are skipped).
+            Its rules are applied after any rules on the command line.
+
+        Notes:
+            Entries that match no rule are kept unchanged, or omitted with
+            --drop-unmatched. The number of entries rewritten by each rule is listed.
+
+            Note that if the output file already exists, the command will be aborted
+            (hashdeep-compare will not overwrite existing files).
+        "
+    )
+}
+
 /// Gets the part function's `clap` "long_about" string
 pub fn long_about_part_string() -> String {
     formatdoc!("
@@ -98,8 +244,137 @@ pub fn help_part_string() -> String {
             subdirectories. Nonexistent subdirectories will not be created; if one is
             specified, the command will be aborted.
 
+            --output-dir DIR writes the output files in a directory instead (created if
+            needed), with fixed names (e.g.: DIR/full_match_pairs). If any already
+            exist, the command will be aborted, unless --force replaces them; with
+            --timestamped, they are written in a new subdirectory named for the current
+            time (UTC, e.g.: DIR/2024-01-31_235959). --skip-empty doesn't write files for
+            empty categories (and with --force, removes existing ones). The directory
+            isn't changed until the logs have been read and partitioned.
+
+            File content is compared by size and by each hash algorithm recorded in
+            both logs (as listed in their headers): e.g., a log made with hashdeep's
+            default settings and a log made with `hashdeep -c md5` will be compared by
+            size and MD5.
+
+            If the logs' file paths have different roots (e.g.: one log was made from
+            /home/user with `hashdeep -lr data/`, and the other from inside data/),
+            most of their entries will only match by hashes. When most \"hashes match\"
+            pairs differ only by the same path prefixes, the prefixes are reported
+            (with whether the logs' \"## Invoked from:\" header lines agree), and
+            --align-roots removes them before partitioning.
+
+            --root1/--root2 PREFIX match a log's entries without a path prefix, and
+            --replace-prefix1/--replace-prefix2 FROM TO match them with a path prefix
+            replaced (prefixes match whole path components, as for `root`; entries
+            that don't start with the prefix are matched unchanged). This is done in
+            memory: the output files list the logs' original paths, as does
+            --align-roots.
+
+            Hashes match pairs are also grouped by directory: when at least 2 pairs
+            (and more than half of file 1's entries in a directory) moved together, a
+            \"directory a/x/ -> b/y/\" finding is written to the directory moves output
+            file, followed by the directory's entries that didn't move with it.
+
+            --json PATH also writes all of the results to a single JSON document: the
+            inputs (paths, hash columns and warnings), the counts and entries of each
+            category (named as the output files' suffixes), and a schema_version that
+            changes whenever a field is changed or removed.
+
+            --summary also prints the changes between the logs, sorted by path (like
+            `git status --short`): A created, D deleted, M modified, R moved/renamed
+            (old -> new), and ? for each entry of a match group (ambiguous), with
+            file sizes. --summary-limit and --summary-offset page through long lists.
+
+            Exit codes:
+                0   the logs are identical (only full match pairs)
+                1   the command failed (e.g.: a log couldn't be read)
+                2   invalid arguments
+                3   the logs differ
+                4   anomalies were found (full match groups or name match groups),
+                    whether or not the logs also differ
+                130 interrupted (e.g.: with Ctrl-C)
+            With --exit-zero, the exit code is 0 whenever partitioning succeeds (the
+            behavior of earlier versions).
+
             Note that if any of the resulting output files already exist, the command
             will be aborted (hashdeep-compare will not overwrite existing files).
         "
     )
-}
\ No newline at end of file
+}
+
+/// Gets the audit function's `clap` "long_about" string
+pub fn long_about_audit_string() -> String {
+    formatdoc!("
+        Partitions the entries of a hashdeep log against a live directory, like
+        `part`, without writing a second log first."
+    )
+}
+
+/// Gets the audit function help string
+pub fn help_audit_string() -> String {
+
+    formatdoc!("
+        Notes:
+            The directory is hashed with the built-in hashing engine (as by
+            `hash --engine native`), using the log's hash algorithms. It takes the
+            place of the second log in `part`: the output files and statistics are
+            the same, with \"file 2\" referring to the directory.
+
+            Paths must be logged the same way on both sides: e.g., a log made with
+            `hash --relative` should be audited with `audit --relative`.
+
+            Files that can't be read are listed in path/to/output_file_base_errors,
+            and summarized on stderr.
+
+            With --skip-unmatchable, files whose size and path both don't appear in
+            the log (so they can't match any log entry) aren't read: they are listed
+            in path/to/output_file_base_skipped_files_file2 (as size,path) instead of
+            in the \"no match\" output file.
+
+            Note that if any of the resulting output files already exist, the command
+            will be aborted before hashing starts (hashdeep-compare will not overwrite
+            existing files).
+        "
+    )
+}
+
+/// Gets the verify function's `clap` "long_about" string
+pub fn long_about_verify_string() -> String {
+    formatdoc!("
+        Rehashes a reproducible random sample of a hashdeep log's files, and
+        reports files that are missing, changed size, or changed contents."
+    )
+}
+
+/// Gets the verify function help string
+pub fn help_verify_string() -> String {
+
+    formatdoc!("
+        Notes:
+            Exactly one of --percent or --bytes must be given. With --percent, the
+            sample count is rounded up to a whole file. With --bytes, files are taken
+            in random order while they fit in the remaining budget (using their
+            logged sizes).
+
+            The same log, sample size and seed always select the same sample. The
+            seed is printed with the results, so a run can be repeated exactly.
+
+            Files are found at their logged paths: relative paths are resolved from
+            --base-dir (e.g.: the target directory of a log made with
+            `hash --relative`). They are hashed with the built-in hashing engine (as
+            by `hash --engine native`), using the log's hash algorithms. Files whose
+            size has changed are not read.
+
+            Statistics are printed, followed by a line for each sampled file that
+            didn't verify.
+
+            Exit codes:
+                0   all sampled files verified
+                1   the command failed (e.g.: the log couldn't be read)
+                2   invalid arguments
+                3   some sampled files are missing, changed, or couldn't be read
+                130 interrupted (e.g.: with Ctrl-C)
+        "
+    )
+}
@@ -6,9 +6,20 @@ pub mod help;
 pub mod sort;
 mod common;
 mod log_entry;
+pub mod digest;
 mod partitioner;
 pub mod partition;
+pub mod partition_report;
+pub mod change_summary;
+mod root_alignment;
 mod some_vec;
 pub mod command;
+pub mod native_hash;
+pub mod hash_errors;
+pub mod audit;
+pub mod verify;
+pub mod archive_hash;
+mod partial_output;
+mod mtimes;
 mod log_ops;
-pub mod root;
\ No newline at end of file
+pub mod root;pub mod rewrite;
@@ -1,46 +1,99 @@
 use std::fmt;
+use std::io::{self, Write};
 
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+use crate::digest::{Digest, HashAlgorithm};
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 /// Represents the content of a single hashdeep file entry line.
 ///
-/// Constructing a string with the form
+/// Writing a LogEntry (with `write_to`) is expected to recreate its source line exactly:
 ///
-/// >`hashes` + "," + `filename`
+/// >`size` + "," + each of `digests` + "," + `filename`
 ///
-/// is expected to recreate the source line exactly.
+/// Filenames are kept as the raw bytes hashdeep wrote, which may not be valid UTF-8.
+/// Displaying a LogEntry gives the same line, but with any invalid UTF-8 in the filename replaced.
 pub struct LogEntry {
-    /// The content section of the line: its size and hash values (excluding the trailing comma)
-    pub hashes: String,
+    /// The file's size in bytes
+    pub size: u64,
+    /// The file's digests, in the column order of the log's header
+    pub digests: Vec<Digest>,
     /// The filename/path section of the line: its location in the filesystem
-    pub filename: String,
+    pub filename: Vec<u8>,
 }
 
 impl LogEntry {
 
-    const HASHCOUNT: usize = 3;
+    /// Creates a LogEntry from a hashdeep log line with the specified hash columns,
+    /// or returns None on failure.
+    ///
+    /// The size must be a decimal number (without leading zeros), and each digest must be
+    /// lowercase hex of its algorithm's exact width: anything else is an invalid line.
+    ///
+    /// The filename is kept as raw bytes, so it doesn't need to be valid UTF-8.
+    pub fn from_bytes(s: &[u8], hash_columns: &[HashAlgorithm]) -> Option<LogEntry> {
+
+        // Note: Commas in the filename will make `sections` longer than `hash_columns.len() + 2`.
+        let mut sections = s.splitn(hash_columns.len() + 2, |&c| c == b',');
+
+        let size = LogEntry::parse_size(std::str::from_utf8(sections.next()?).ok()?)?;
+        let digests = hash_columns.iter()
+            .map(|&algorithm| {
+                let section = std::str::from_utf8(sections.next()?).ok()?;
+                Digest::from_hex(algorithm, section)
+            })
+            .collect::<Option<Vec<Digest>>>()?;
+
+        let filename = sections.next()?.to_vec();
+        if filename.is_empty() {return None;}
 
-    /// Creates a LogEntry from a hashdeep log line, or returns None on failure.
-    pub fn from_str(s: &str) -> Option<LogEntry> {
+        Some(LogEntry{size, digests, filename})
+    }
 
-        // Note: Commas in the filename will make `sections` longer than `LogEntry::HASHCOUNT + 1`.
-        let sections: Vec<&str> = s.split(',').collect();
-        if sections.len() < LogEntry::HASHCOUNT + 1 {return None;}
+    /// Parses a size field, rejecting any text that would not be recreated by displaying the result.
+    fn parse_size(s: &str) -> Option<u64> {
 
-        let (hashes_sections, filename_sections) = sections.split_at(LogEntry::HASHCOUNT);
-        if hashes_sections.contains(&"") {return None;}
+        if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {return None;}
+        if s.len() > 1 && s.starts_with('0') {return None;}
 
-        let hashes = hashes_sections.join(",");
-        let filename = filename_sections.join(",");
-        if filename.is_empty() {return None;}
+        s.parse().ok()
+    }
 
-        Some(LogEntry{hashes, filename})
+    /// Gets this entry's digest for `algorithm`, if it has one.
+    pub fn digest(&self, algorithm: HashAlgorithm) -> Option<&Digest> {
+        self.digests.iter().find(|x| x.algorithm() == algorithm)
+    }
+
+    /// Gets the values that identify this entry's file content: its size, plus its digests
+    /// for `algorithms` (in that order).
+    ///
+    /// Digests for algorithms that this entry doesn't have are skipped.
+    pub fn content_key(&self, algorithms: &[HashAlgorithm]) -> (u64, Vec<Digest>) {
+        (self.size, algorithms.iter().filter_map(|&x| self.digest(x).copied()).collect())
+    }
+
+    /// Writes this entry's log line (without a line ending), with its filename's exact bytes.
+    ///
+    /// # Errors
+    ///
+    /// Any error encountered while writing will be returned.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        write!(w, "{}", self.size)?;
+        for digest in &self.digests {
+            write!(w, ",{digest}")?;
+        }
+        w.write_all(b",")?;
+        w.write_all(&self.filename)
     }
 }
 
 impl fmt::Display for LogEntry {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{},{}", self.hashes, self.filename)
+        write!(f, "{}", self.size)?;
+        for digest in &self.digests {
+            write!(f, ",{digest}")?;
+        }
+        write!(f, ",{}", String::from_utf8_lossy(&self.filename))
     }
 }
 
@@ -49,43 +102,144 @@ impl fmt::Display for LogEntry {
 mod test {
     use super::*;
 
+    const MD5_STR: &str = "4692d489b0638e49682df4f46dacd3c3";
+    const SHA256_STR: &str = "0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715";
+
+    const DEFAULT_COLUMNS: [HashAlgorithm; 2] = HashAlgorithm::DEFAULT_COLUMNS;
+
+    fn entry(size: u64, filename: &str) -> LogEntry {
+        LogEntry{
+            size,
+            digests: vec![
+                Digest::from_hex(HashAlgorithm::Md5, MD5_STR).unwrap(),
+                Digest::from_hex(HashAlgorithm::Sha256, SHA256_STR).unwrap(),
+            ],
+            filename: filename.as_bytes().to_vec(),
+        }
+    }
+
     #[test]
     fn to_string_test() {
-        let hashes = "1,aaaaa,bbbbbbb".to_owned();
-        let filename = "theDir/theFile.ext".to_owned();
-        let le = LogEntry{hashes, filename};
-        assert_eq!(le.to_string(), "1,aaaaa,bbbbbbb,theDir/theFile.ext".to_owned());
+        let le = entry(1, "theDir/theFile.ext");
+        assert_eq!(le.to_string(), format!("1,{MD5_STR},{SHA256_STR},theDir/theFile.ext"));
     }
 
     #[test]
-    fn from_str_test() {
+    fn from_bytes_test() {
         let random_chars = "[l]425[o24h8j5ffp983h4f";
-        assert_eq!(LogEntry::from_str(random_chars), None);
+        assert_eq!(LogEntry::from_bytes(random_chars.as_bytes(), &DEFAULT_COLUMNS), None);
 
         let not_enough_commas = "4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
-        assert_eq!(LogEntry::from_str(not_enough_commas), None);
+        assert_eq!(LogEntry::from_bytes(not_enough_commas.as_bytes(), &DEFAULT_COLUMNS), None);
 
         let no_size = ",4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
-        assert_eq!(LogEntry::from_str(no_size), None);
+        assert_eq!(LogEntry::from_bytes(no_size.as_bytes(), &DEFAULT_COLUMNS), None);
 
         let empty_filename = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,";
-        assert_eq!(LogEntry::from_str(empty_filename), None);
+        assert_eq!(LogEntry::from_bytes(empty_filename.as_bytes(), &DEFAULT_COLUMNS), None);
 
         let just_commas = ",,,";
-        assert_eq!(LogEntry::from_str(just_commas), None);
-
-        let hashes_str = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715";
+        assert_eq!(LogEntry::from_bytes(just_commas.as_bytes(), &DEFAULT_COLUMNS), None);
 
         let normal_entry = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
-        assert_eq!(LogEntry::from_str(normal_entry),
-                   Some(LogEntry{hashes: hashes_str.to_owned(), filename: "hashdeepComp/345.txt".to_owned()}));
+        assert_eq!(LogEntry::from_bytes(normal_entry.as_bytes(), &DEFAULT_COLUMNS), Some(entry(4, "hashdeepComp/345.txt")));
 
         let non_ascii_filename = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/Γεια σου.txt";
-        assert_eq!(LogEntry::from_str( non_ascii_filename),
-                   Some(LogEntry{hashes: hashes_str.to_owned(), filename: "hashdeepComp/Γεια σου.txt".to_owned()}));
+        assert_eq!(LogEntry::from_bytes(non_ascii_filename.as_bytes(), &DEFAULT_COLUMNS), Some(entry(4, "hashdeepComp/Γεια σου.txt")));
 
         let commas_in_filename = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/3,4,,5.txt,";
-        assert_eq!(LogEntry::from_str(commas_in_filename),
-                   Some(LogEntry{hashes: hashes_str.to_owned(), filename: "hashdeepComp/3,4,,5.txt,".to_owned()}));
+        assert_eq!(LogEntry::from_bytes(commas_in_filename.as_bytes(), &DEFAULT_COLUMNS), Some(entry(4, "hashdeepComp/3,4,,5.txt,")));
+
+        let zero_size = "0,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(zero_size.as_bytes(), &DEFAULT_COLUMNS), Some(entry(0, "hashdeepComp/345.txt")));
+    }
+
+    #[test]
+    fn from_bytes_invalid_fields_test() {
+        let non_numeric_size = "4x,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(non_numeric_size.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let negative_size = "-4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(negative_size.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let leading_zero_size = "04,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(leading_zero_size.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let overflowing_size = "18446744073709551616,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(overflowing_size.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let short_md5 = "4,4692d489b0638e49682df4f46dacd3c,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(short_md5.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let non_hex_md5 = "4,4692d489b0638e49682df4f46dacd3cz,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(non_hex_md5.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let long_sha256 = "4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a7150,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(long_sha256.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let swapped_digests = "4,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,4692d489b0638e49682df4f46dacd3c3,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(swapped_digests.as_bytes(), &DEFAULT_COLUMNS), None);
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let line = "18446744073709551615,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/3,4,,5.txt,";
+        assert_eq!(LogEntry::from_bytes(line.as_bytes(), &DEFAULT_COLUMNS).unwrap().to_string(), line);
+    }
+
+    #[test]
+    fn non_utf8_filename_test() {
+        let line = b"4,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/caf\xe9,.txt";
+        let entry = LogEntry::from_bytes(line, &DEFAULT_COLUMNS).unwrap();
+
+        assert_eq!(entry.filename, b"hashdeepComp/caf\xe9,.txt");
+        let mut written = Vec::new();
+        entry.write_to(&mut written).unwrap();
+        assert_eq!(written, line);
+        assert!(entry.to_string().ends_with(",hashdeepComp/caf\u{FFFD},.txt"));
+
+        let non_utf8_size = b"4\xe9,4692d489b0638e49682df4f46dacd3c3,0c47cda934d53d7ca29d822a59531dcf6d36cbd9740a4fd0b867a0343910a715,hashdeepComp/345.txt";
+        assert_eq!(LogEntry::from_bytes(non_utf8_size, &DEFAULT_COLUMNS), None);
+    }
+
+    #[test]
+    fn from_bytes_hash_columns_test() {
+        use HashAlgorithm::*;
+
+        let md5_only = "4,4692d489b0638e49682df4f46dacd3c3,hashdeepComp/345.txt";
+        let entry = LogEntry::from_bytes(md5_only.as_bytes(), &[Md5]).unwrap();
+        assert_eq!(entry.digests, vec![Digest::from_hex(Md5, MD5_STR).unwrap()]);
+        assert_eq!(entry.filename, b"hashdeepComp/345.txt");
+        assert_eq!(entry.to_string(), md5_only);
+
+        //with default columns, the filename would be read as a SHA-256 digest
+        assert_eq!(LogEntry::from_bytes(md5_only.as_bytes(), &DEFAULT_COLUMNS), None);
+
+        let sha1_tiger_whirlpool = concat!(
+            "4,da39a3ee5e6b4b0d3255bfef95601890afd80709,",
+            "3293ac630c13f0245f92bbb1766e16167a4e58492dde73f3,",
+            "19fa61d75522a4669b44e39c1d2e1726c530232130d407f89afee0964997f7a7",
+            "3e83be698b288febcf88e3e03c4f0757ea8964e59b63d93708b138cc42a66eb3,",
+            "hashdeepComp/3,4,,5.txt,",
+        );
+        let entry = LogEntry::from_bytes(sha1_tiger_whirlpool.as_bytes(), &[Sha1, Tiger, Whirlpool]).unwrap();
+        assert_eq!(entry.digests.iter().map(Digest::algorithm).collect::<Vec<_>>(), vec![Sha1, Tiger, Whirlpool]);
+        assert_eq!(entry.filename, b"hashdeepComp/3,4,,5.txt,");
+        assert_eq!(entry.to_string(), sha1_tiger_whirlpool);
+
+        assert_eq!(LogEntry::from_bytes(sha1_tiger_whirlpool.as_bytes(), &[Sha1, Whirlpool, Tiger]), None);
+    }
+
+    #[test]
+    fn content_key_test() {
+        use HashAlgorithm::*;
+
+        let entry = entry(4, "hashdeepComp/345.txt");
+        let md5 = Digest::from_hex(Md5, MD5_STR).unwrap();
+        let sha256 = Digest::from_hex(Sha256, SHA256_STR).unwrap();
+
+        assert_eq!(entry.content_key(&[Md5, Sha256]), (4, vec![md5, sha256]));
+        assert_eq!(entry.content_key(&[Sha256]), (4, vec![sha256]));
+        assert_eq!(entry.content_key(&[Sha1, Md5]), (4, vec![md5]));
     }
-}
\ No newline at end of file
+}
@@ -1,30 +1,336 @@
 //! Hashdeep log operations
 
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
 use crate::common;
-use crate::common::{HashdeepLogHeaderWarning, LogFile};
+use crate::common::{HashdeepLogHeaderWarning, LogFileWriter, LogReader};
+use crate::digest::{Digest, HashAlgorithm};
 use crate::log_entry::LogEntry;
 
-/// Processes a hashdeep log:
-/// 1. Reads a hashdeep log file's contents into a `LogFile`.
-/// 2. Runs `f` on that `LogFile` to process it.
-/// 3. Writes the resulting `LogFile` contents to a new file.
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SortTempFileError {
+    #[error("\"{0}\" cannot be used for temporary files (not found)")]
+    DirectoryNotFound(String),
+
+    #[error("a temporary directory cannot be created in \"{0}\" ({1})")]
+    CreateDirectory(String, #[source] std::io::Error),
+
+    #[error("a temporary file cannot be written in \"{0}\" ({1})")]
+    Write(String, #[source] std::io::Error),
+}
+
+/// Reads a hashdeep log file and writes its entries to a new file, sorted by `compare`.
+///
+/// Entries are held in memory up to an estimated `memory_budget` bytes: above that, sorted runs of
+/// entries are written to temporary files (in a new temporary directory in `temp_directory`, or in the
+/// system's temporary directory), then merged into the output file. The output is the same either way.
+/// (`compare` must be a total order, so entries have only 1 sorted order.)
+///
+/// At most `MAX_MERGED_RUNS` run files are open at once: if there are more, they are first merged
+/// into fewer, longer runs (in as many passes as needed).
+///
+/// If an error occurs, the incomplete output file is removed.
+///
+/// On success, returns a Vec of warning strings, if any warnings were emitted while reading the file.
+///
+/// # Errors
+///
+/// Any error emitted while reading or writing the files will be returned.
+pub fn process_log_sorted<T>(
+    filename: &str,
+    out_filename: &str,
+    compare: T,
+    memory_budget: usize,
+    temp_directory: Option<&str>,
+) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering
+{
+    if std::path::Path::exists(out_filename.as_ref()) {
+        return Err(common::WriteToFileError::OutputFileExists(out_filename.to_string()).into());
+    }
+
+    let mut log_reader = LogReader::open(filename)?;
+    let hash_columns = log_reader.hash_columns().to_vec();
+
+    //read sorted runs, spilling them to temporary files until the rest of the log fits in memory
+    let mut spilled_runs = SpilledRuns::default();
+    let last_run = loop {
+        let mut run = vec![];
+        let mut run_size = 0;
+        let mut log_exhausted = true;
+        for item in &mut log_reader {
+            let item = item.map_err(|e| common::ReadLogEntriesFromFileError::OtherIoError(filename.to_string(), e))?;
+
+            // invalid lines are counted by log_reader, and omitted from the output
+            if let Ok(log_entry) = item {
+                run_size += estimated_memory_use(&log_entry);
+                run.push(log_entry);
+            }
+            if run_size >= memory_budget {
+                log_exhausted = false;
+                break;
+            }
+        }
+        run.sort_by(&compare);
+
+        if log_exhausted {
+            break run;
+        }
+        spilled_runs.write(run, temp_directory)?;
+    };
+
+    let mut header_lines = log_reader.header_lines().to_vec();
+    add_header_note(log_reader.header_warnings(), &mut header_lines);
+
+    //(the in-memory last run is merged with the spilled runs, so it takes one of the open runs' places)
+    spilled_runs.merge_down_to(MAX_MERGED_RUNS - 1, MAX_MERGED_RUNS, &hash_columns, &compare)?;
+
+    let mut log_file_writer = LogFileWriter::create(out_filename, &header_lines)?;
+
+    let write_entries = || -> Result<(), Box<dyn std::error::Error>> {
+        let mut runs = spilled_runs.readers(&hash_columns)?;
+        runs.push(Box::new(last_run.into_iter().map(Ok)));
+
+        merge_runs(runs, &compare, |log_entry| log_file_writer.write_entry(&log_entry).map_err(Into::into))?;
+        log_file_writer.finish()?;
+        Ok(())
+    };
+
+    if let Err(e) = write_entries() {
+        // the output file was created above, so this should only fail if it was removed externally
+        let _ = std::fs::remove_file(out_filename);
+        return Err(e);
+    }
+
+    Ok(log_reader.warning_report())
+}
+
+/// The maximum number of sorted runs that are merged (and so, the number of run files open) at once
+const MAX_MERGED_RUNS: usize = 64;
+
+/// The first entry of a sorted run, ordered by `compare` (then by run, so equal entries stay in run order)
+struct MergeHead<'c, T> {
+    log_entry: LogEntry,
+    run: usize,
+    compare: &'c T,
+}
+
+impl<T> Ord for MergeHead<'_, T>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(&self.log_entry, &other.log_entry).then(self.run.cmp(&other.run))
+    }
+}
+
+impl<T> PartialOrd for MergeHead<'_, T>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for MergeHead<'_, T>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for MergeHead<'_, T>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering
+{}
+
+/// Merges sorted runs (a k-way merge), passing their entries to `write` in sorted order.
+fn merge_runs<T, W>(mut runs: Vec<RunReader>, compare: &T, mut write: W) -> Result<(), Box<dyn std::error::Error>>
+    where T: Fn(&LogEntry, &LogEntry) -> Ordering,
+          W: FnMut(LogEntry) -> Result<(), Box<dyn std::error::Error>>
+{
+    //a min-heap of the runs' first entries
+    let mut heads = BinaryHeap::with_capacity(runs.len());
+    for (run, reader) in runs.iter_mut().enumerate() {
+        if let Some(log_entry) = reader.next().transpose()? {
+            heads.push(Reverse(MergeHead{log_entry, run, compare}));
+        }
+    }
+
+    while let Some(Reverse(MergeHead{log_entry, run, ..})) = heads.pop() {
+        if let Some(next) = runs[run].next().transpose()? {
+            heads.push(Reverse(MergeHead{log_entry: next, run, compare}));
+        }
+        write(log_entry)?;
+    }
+    Ok(())
+}
+
+/// Estimates the memory used by a log entry (in bytes).
+fn estimated_memory_use(log_entry: &LogEntry) -> usize {
+    std::mem::size_of::<LogEntry>()
+        + log_entry.filename.capacity()
+        + log_entry.digests.capacity() * std::mem::size_of::<Digest>()
+}
+
+/// Sorted runs of log entries, written (as log entry lines) to files in a temporary directory
+#[derive(Default)]
+struct SpilledRuns {
+    temp_dir: Option<tempfile::TempDir>,
+    paths: Vec<PathBuf>,
+    /// The number of run files that have been written (including merged ones), to name new ones
+    files_written: usize,
+}
+
+impl SpilledRuns {
+
+    /// Writes a sorted run to a new file (creating the temporary directory if it doesn't exist yet).
+    fn write(&mut self, run: Vec<LogEntry>, temp_directory: Option<&str>) -> Result<(), SortTempFileError> {
+
+        let temp_dir = match &mut self.temp_dir {
+            Some(temp_dir) => temp_dir,
+            None => {
+                let mut builder = tempfile::Builder::new();
+                builder.prefix("hashdeep-compare-sort");
+                let temp_dir = match temp_directory {
+                    Some(temp_directory) => builder.tempdir_in(temp_directory),
+                    None => builder.tempdir(),
+                };
+                let temp_directory = temp_directory.map_or_else(
+                    || std::env::temp_dir().to_string_lossy().into_owned(),
+                    |x| x.to_string());
+                //(tempfile's errors include the random name of the directory it tried to create)
+                self.temp_dir.insert(temp_dir.map_err(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => SortTempFileError::DirectoryNotFound(temp_directory),
+                    kind => SortTempFileError::CreateDirectory(temp_directory, kind.into()),
+                })?)
+            },
+        };
+
+        let path = temp_dir.path().join(format!("run{}", self.files_written));
+        let write_run = || -> std::io::Result<()> {
+            let mut file = BufWriter::new(File::create(&path)?);
+            for log_entry in run {
+                log_entry.write_to(&mut file)?;
+                file.write_all(b"\n")?;
+            }
+            file.flush()
+        };
+        write_run().map_err(|e| SortTempFileError::Write(temp_dir.path().to_string_lossy().into_owned(), e))?;
+
+        self.files_written += 1;
+        self.paths.push(path);
+        Ok(())
+    }
+
+    /// Merges runs, `merge_width` at a time, into new runs, until at most `max_runs` are left.
+    fn merge_down_to<T>(&mut self, max_runs: usize, merge_width: usize, hash_columns: &[HashAlgorithm], compare: &T)
+        -> Result<(), Box<dyn std::error::Error>>
+        where T: Fn(&LogEntry, &LogEntry) -> Ordering
+    {
+        while self.paths.len() > max_runs {
+            //merging fewer runs than `merge_width` in the last pass keeps the runs' lengths more even
+            let count = merge_width.min(self.paths.len() - max_runs + 1);
+            let merged_paths = self.paths.drain(..count).collect::<Vec<_>>();
+
+            let temp_dir = self.temp_dir.as_ref().expect("runs are only spilled to a temporary directory");
+            let path = temp_dir.path().join(format!("run{}", self.files_written));
+            let write_error = |e| SortTempFileError::Write(temp_dir.path().to_string_lossy().into_owned(), e);
+
+            let mut file = BufWriter::new(File::create(&path).map_err(write_error)?);
+            let readers = merged_paths.iter().map(|x| run_reader(x, hash_columns)).collect::<std::io::Result<Vec<_>>>()?;
+            merge_runs(readers, compare, |log_entry| {
+                log_entry.write_to(&mut file)
+                    .and_then(|()| file.write_all(b"\n"))
+                    .map_err(|e| write_error(e).into())
+            })?;
+            file.flush().map_err(write_error)?;
+
+            for merged_path in merged_paths {
+                std::fs::remove_file(merged_path)?;
+            }
+            self.files_written += 1;
+            self.paths.push(path);
+        }
+        Ok(())
+    }
+
+    /// Opens the runs' files, as iterators of their entries.
+    fn readers<'a>(&self, hash_columns: &'a [HashAlgorithm]) -> std::io::Result<Vec<RunReader<'a>>> {
+        self.paths.iter().map(|path| run_reader(path, hash_columns)).collect()
+    }
+}
+
+/// Opens a run's file, as an iterator of its entries.
+fn run_reader<'a>(path: &Path, hash_columns: &'a [HashAlgorithm]) -> std::io::Result<RunReader<'a>> {
+    let lines = BufReader::new(File::open(path)?).split(b'\n');
+    Ok(Box::new(lines.map(|line| {
+        LogEntry::from_bytes(&line?, hash_columns)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid line in a sorted run file"))
+    })))
+}
+
+/// The entries of a sorted run, in order
+type RunReader<'a> = Box<dyn Iterator<Item = std::io::Result<LogEntry>> + 'a>;
+
+/// Processes a hashdeep log one entry at a time, without loading all of its entries:
+/// 1. Reads a hashdeep log file's header, and writes it to a new file.
+/// 2. Runs `f` on each log entry as it is read.
+/// 3. Writes each entry returned by `f` to the new file (entries for which `f` returns None are omitted).
+///
+/// If an error occurs (including an error returned by `f`, which stops processing), the incomplete output file is removed.
 ///
 /// On success, returns a Vec of warning strings, if any warnings were emitted while reading the file.
 ///
 /// # Errors
 ///
 /// Any error emitted while reading or writing the files will be returned.
-pub fn process_log<T>(filename: &str, out_filename: &str, mut f: T)
+pub fn process_log_entries<T>(filename: &str, out_filename: &str, mut f: T)
     -> Result<Option<Vec<String>>, Box<dyn std::error::Error>>
-    where T: FnMut(&mut LogFile<Vec<LogEntry>>)
+    where T: FnMut(LogEntry) -> Result<Option<LogEntry>, Box<dyn std::error::Error>>
 {
     if std::path::Path::exists(out_filename.as_ref()) {
         return Err(common::WriteToFileError::OutputFileExists(out_filename.to_string()).into());
     }
 
-    let mut log_file = common::read_log_entries_from_file::<Vec<LogEntry>>(filename)?;
+    let mut log_reader = LogReader::open(filename)?;
+
+    let mut header_lines = log_reader.header_lines().to_vec();
+    add_header_note(log_reader.header_warnings(), &mut header_lines);
+
+    let mut log_file_writer = LogFileWriter::create(out_filename, &header_lines)?;
+
+    let write_entries = || -> Result<(), Box<dyn std::error::Error>> {
+        for item in &mut log_reader {
+            let item = item.map_err(|e| common::ReadLogEntriesFromFileError::OtherIoError(filename.to_string(), e))?;
+
+            // invalid lines are counted by log_reader, and omitted from the output
+            let Ok(log_entry) = item else {continue};
+            if let Some(log_entry) = f(log_entry)? {
+                log_file_writer.write_entry(&log_entry)?;
+            }
+        }
+        log_file_writer.finish()?;
+        Ok(())
+    };
+
+    if let Err(e) = write_entries() {
+        // the output file was created above, so this should only fail if it was removed externally
+        let _ = std::fs::remove_file(out_filename);
+        return Err(e);
+    }
+
+    Ok(log_reader.warning_report())
+}
 
-    f(&mut log_file);
+/// Unless any disqualifying header warnings are found,
+/// adds a note to the 5th line of the header.
+fn add_header_note(header_warnings: &[HashdeepLogHeaderWarning], header_lines: &mut [Vec<u8>]) {
 
     fn should_skip_header_note(warning: &HashdeepLogHeaderWarning) -> bool {
         matches!(warning,
@@ -34,17 +340,48 @@ pub fn process_log<T>(filename: &str, out_filename: &str, mut f: T)
         )
     }
 
-    // Unless any disqualifying header warnings are found,
-    // add a note to the 5th line of the header.
-    if ! log_file.header_warnings.iter().any(should_skip_header_note) {
+    if ! header_warnings.iter().any(should_skip_header_note) {
         const VERSION: &str = env!("CARGO_PKG_VERSION");
-        if let Some(line) = log_file.header_lines.get_mut(4) {
-            *line = format!("## Modified by hashdeep-compare v{VERSION}");
+        if let Some(line) = header_lines.get_mut(4) {
+            *line = format!("## Modified by hashdeep-compare v{VERSION}").into_bytes();
         }
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    let warning_report = log_file.warning_report();
+    const MD5_STR: &str = "4692d489b0638e49682df4f46dacd3c3";
+
+    fn entry(size: u64) -> LogEntry {
+        LogEntry{
+            size,
+            digests: vec![Digest::from_hex(HashAlgorithm::Md5, MD5_STR).unwrap()],
+            filename: format!("file{size}").into_bytes(),
+        }
+    }
 
-    common::write_log_file_to_file(log_file, out_filename)?;
-    Ok(warning_report)
-}
\ No newline at end of file
+    #[test]
+    fn merge_down_to_test() {
+        let hash_columns = [HashAlgorithm::Md5];
+        let compare = |a: &LogEntry, b: &LogEntry| a.size.cmp(&b.size);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_directory = temp_dir.path().to_str();
+
+        //10 interleaved runs of 5 entries
+        let mut spilled_runs = SpilledRuns::default();
+        for i in 0..10 {
+            spilled_runs.write((0..5).map(|x| entry(x * 10 + i)).collect(), temp_directory).unwrap();
+        }
+
+        spilled_runs.merge_down_to(4, 3, &hash_columns, &compare).unwrap();
+        assert_eq!(spilled_runs.paths.len(), 4);
+        let run_files = std::fs::read_dir(spilled_runs.temp_dir.as_ref().unwrap().path()).unwrap().count();
+        assert_eq!(run_files, 4);
+
+        let mut merged = vec![];
+        merge_runs(spilled_runs.readers(&hash_columns).unwrap(), &compare, |x| { merged.push(x.size); Ok(()) }).unwrap();
+        assert_eq!(merged, (0..50).collect::<Vec<_>>());
+    }
+}
@@ -6,7 +6,6 @@ use hashdeep_compare::main_impl::main_io_wrapper;
 /// and exits with the resulting exit code or error
 ///
 /// See main_impl.rs for more details.
-
 fn main() -> Result<(), Box<dyn Error>> {
 
     let args: Vec<String> = std::env::args().collect();
@@ -36,7 +36,8 @@
 use crate::*;
 use std::error::Error;
 use std::io::Write;
-use clap::{Parser, Subcommand};
+use std::num::NonZeroUsize;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 
 /// Specifies program arguments and (re)direction of stdout/stderr, then runs the program
 ///
@@ -54,13 +55,12 @@ pub fn main_io_wrapper(
     let exit_code =
     match main_impl(args, &mut stdout, &mut stderr)
     {
-        Ok(()) => 0,
+        Ok(exit_code) => exit_code,
         Err(err) => {
             if let Some(err) = err.downcast_ref::<clap::Error>() {
                 if err.use_stderr() {
                     write! (stderr, "{err}")?;
-                    // Code 2 on error matches `clap`'s behavior.
-                    2
+                    INVALID_ARGUMENTS_EXIT_CODE
                 }
                 else {
                     write! (stdout, "{err}")?;
@@ -81,11 +81,26 @@ pub fn main_io_wrapper(
                 else if let Some(err) = err.downcast_ref::<partitioner::MatchPartitionError>() {
                     writeln! (stderr, "Error: \"{err}\"")?;
                 }
+                else if let Some(err) = err.downcast_ref::<partition::PartitionLogError>() {
+                    writeln! (stderr, "Error: \"{err}\"")?;
+                }
+                else if let Some(err) = err.downcast_ref::<archive_hash::HashArchiveError>() {
+                    writeln! (stderr, "Error: \"{err}\"")?;
+                }
+                else if let Some(err) = err.downcast_ref::<log_ops::SortTempFileError>() {
+                    writeln! (stderr, "Error: \"{err}\"")?;
+                }
+                else if let Some(err) = err.downcast_ref::<root::ChangeRootError>() {
+                    writeln! (stderr, "Error: \"{err}\"")?;
+                }
+                else if let Some(err) = err.downcast_ref::<rewrite::RewriteRuleError>() {
+                    writeln! (stderr, "Error: \"{err}\"")?;
+                }
                 else {
                     writeln! (stderr, "Error: {err:?}")?;
                 }
 
-                1
+                ERROR_EXIT_CODE
             }
         }
     };
@@ -112,10 +127,29 @@ fn write_lines (writer: &mut impl Write, lines: Vec<String>) -> Result<(), Box<d
     lines.iter().try_for_each(|line| writeln!(writer, "{line}").map_err(Into::into))
 }
 
+// Exit codes, shared by all subcommands (also listed in the README, and in the help of the commands that use 3 and 4).
+// Like `diff`, a command that ran successfully but found something to report uses a nonzero code,
+//  above the codes for failures.
+
+/// The exit code for a command that failed
+pub const ERROR_EXIT_CODE: i32 = 1;
+
+/// The exit code for invalid arguments (matches `clap`'s behavior)
+pub const INVALID_ARGUMENTS_EXIT_CODE: i32 = 2;
+
+/// The exit code for a successful comparison or check that found differences or problems
+/// (`part`: the logs differ; `verify`: sampled files didn't verify)
+pub const DIFFERENCES_FOUND_EXIT_CODE: i32 = 3;
+
+/// The exit code for a successful `part` that found anomalies (full match groups or name match groups)
+pub const ANOMALIES_FOUND_EXIT_CODE: i32 = 4;
+
 /// Called by main_io_wrapper: Accepts program arguments and runs the program
 ///
+/// Returns the program's exit code on success
+///
 /// (This was the main() function before the **integration_test_coverage** feature was added)
-fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) -> Result<(), Box<dyn Error>> {
+fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) -> Result<i32, Box<dyn Error>> {
 
     const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -126,6 +160,28 @@ fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) ->
         command: Commands,
     }
 
+    #[derive(ValueEnum, Clone, Copy, Debug)]
+    enum HashEngine {
+        /// Run the external hashdeep binary
+        Hashdeep,
+        /// Use hashdeep-compare's built-in hashing engine
+        Native,
+    }
+
+    #[derive(ValueEnum, Clone, Copy, Debug)]
+    enum SortKey {
+        /// By file path, as bytes
+        Name,
+        /// By hashes, so files with the same content are adjacent
+        Hashes,
+        /// By size, largest first
+        Size,
+        /// By file path, 1 path component at a time (a/b before a.b/c)
+        Path,
+        /// By file path, with numbers compared by value (file2 before file10)
+        Natural,
+    }
+
     #[derive(Subcommand, Debug)]
     #[command(disable_help_flag = true)]
     enum Commands {
@@ -139,15 +195,75 @@ fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) ->
             target_directory: String,
             #[arg(hide_long_help = true, id="path/to/output_log.txt")]
             output_path_base: String,
+            /// The hashing engine that generates the log
+            #[arg(long, value_enum, default_value_t = HashEngine::Hashdeep)]
+            engine: HashEngine,
+            /// Hash from inside the target directory, so logged paths are relative to it (e.g.: ./dir/file)
+            #[arg(long)]
+            relative: bool,
+            /// The hash algorithms to record, as a comma-separated list
+            /// (any of: md5, sha1, sha256, tiger, whirlpool) [default: md5,sha256]
+            #[arg(long, value_name = "LIST", value_parser = digest::HashAlgorithm::list_from_names)]
+            //(`std::vec::Vec` keeps clap from treating this as a multiple-value argument)
+            algorithms: Option<std::vec::Vec<digest::HashAlgorithm>>,
+            /// The number of files to hash in parallel [default: the number of CPUs]
+            #[arg(long, value_name = "N")]
+            jobs: Option<NonZeroUsize>,
+            /// The maximum number of files to read at the same time (native engine only) [default: same as --jobs]
+            #[arg(long, value_name = "N")]
+            max_reads: Option<NonZeroUsize>,
+            /// Write a modification time file with the log, so it can be used with --previous (native engine only)
+            #[arg(long)]
+            save_mtimes: bool,
+            /// Reuse the entries of a previous log (written with --save-mtimes) for files with unchanged
+            /// sizes and modification times (native engine only; implies --save-mtimes)
+            #[arg(long, value_name = "path/to/previous_log.txt")]
+            previous: Option<String>,
+            /// The file types to hash, as hashdeep -o letters (hashdeep engine only) [default: f]
+            #[arg(long, value_name = "TYPES", value_parser = command::parse_file_types)]
+            file_types: Option<String>,
+            /// The hashdeep binary to run, by name or path (hashdeep engine only) [default: hashdeep]
+            #[arg(long, value_name = "BINARY")]
+            hashdeep_binary: Option<String>,
+        },
+        #[command(after_long_help = help::help_hash_archive_string())]
+        #[command(long_about = help::long_about_hash_archive_string())]
+        /// Hash the members of a tar or zip archive into a hashdeep log
+        HashArchive {
+            #[arg(hide_long_help = true, id="path/to/archive")]
+            archive: String,
+            #[arg(hide_long_help = true, id="path/to/output_log.txt")]
+            output_path: String,
+            /// Log member paths relative to the archive root (e.g.: ./dir/file), like `hash --relative`
+            #[arg(long)]
+            relative: bool,
+            /// The hash algorithms to record, as a comma-separated list
+            /// (any of: md5, sha1, sha256, tiger, whirlpool) [default: md5,sha256]
+            #[arg(long, value_name = "LIST", value_parser = digest::HashAlgorithm::list_from_names)]
+            //(`std::vec::Vec` keeps clap from treating this as a multiple-value argument)
+            algorithms: Option<std::vec::Vec<digest::HashAlgorithm>>,
         },
         #[command(after_long_help = help::help_sort_string())]
         #[command(long_about = help::long_about_sort_string())]
-        /// Sort a hashdeep log (by file path)
+        /// Sort a hashdeep log (by file path, or another key)
         Sort {
             #[arg(hide_long_help = true, id="path/to/unsorted_input.txt")]
             input_file: String,
             #[arg(hide_long_help = true, id="path/to/sorted_output.txt")]
             output_file: String,
+            /// The order to sort entries in
+            #[arg(long, value_enum, default_value_t = SortKey::Name)]
+            key: SortKey,
+            /// Reverse the order of the sort key
+            #[arg(long)]
+            reverse: bool,
+            /// The memory that log entries may use: larger logs are sorted in parts, using temporary files
+            /// (a number of bytes, or with a K, M, G or T suffix) [default: 1G]
+            #[arg(long, value_name = "SIZE", value_parser = common::parse_byte_count)]
+            memory_limit: Option<u64>,
+            /// The directory to write temporary files in [default: the system's temporary directory]
+            #[arg(long, value_name = "DIR")]
+            temp_dir: Option<String>,
         },
         #[command(after_long_help = help::help_root_string())]
         #[command(long_about = help::long_about_root_string())]
@@ -159,6 +275,27 @@ fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) ->
             output_file: String,
             #[arg(hide_long_help = true, id="filepath prefix")]
             file_path_prefix: String,
+            /// Remove the prefix as text, without matching whole path components
+            #[arg(long)]
+            raw: bool,
+        },
+        #[command(after_long_help = help::help_rewrite_string())]
+        #[command(long_about = help::long_about_rewrite_string())]
+        /// Rewrite the filepaths of a hashdeep log with ordered rules
+        Rewrite {
+            #[arg(hide_long_help = true, id="path/to/input.txt")]
+            input_file: String,
+            #[arg(hide_long_help = true, id="path/to/output.txt")]
+            output_file: String,
+            /// Rewrite rules, as words: add PREFIX, replace FROM TO, regex PATTERN REPLACEMENT
+            #[arg(value_name = "RULE")]
+            rules: Vec<String>,
+            /// Read more rules from a file (applied after the command line rules)
+            #[arg(long, value_name = "path/to/rules.txt")]
+            rules_file: Option<String>,
+            /// Omit entries that match no rule (by default, they are kept unchanged)
+            #[arg(long)]
+            drop_unmatched: bool,
         },
         #[command(after_long_help = help::help_part_string())]
         #[command(long_about = help::long_about_part_string())]
@@ -168,53 +305,340 @@ fn main_impl(args: &[&str], stdout: &mut impl Write, stderr: &mut impl Write) ->
             input_file1: String,
             #[arg(hide_long_help = true, id="path/to/second_log.txt")]
             input_file2: String,
+            #[arg(hide_long_help = true, id="path/to/output_file_base", required_unless_present = "output_dir")]
+            output_file_base: Option<String>,
+            /// Write the output files, with fixed names, in this directory (created if it doesn't exist) instead
+            #[arg(long, value_name = "DIR", conflicts_with = "path/to/output_file_base")]
+            output_dir: Option<String>,
+            /// With --output-dir, replace output files that already exist in the directory
+            #[arg(long, requires = "output_dir", conflicts_with = "path/to/output_file_base")]
+            force: bool,
+            /// With --output-dir, write the output files in a new subdirectory named for the current time (UTC)
+            #[arg(long, requires = "output_dir", conflicts_with_all = ["force", "path/to/output_file_base"])]
+            timestamped: bool,
+            /// Don't write output files for empty categories
+            #[arg(long)]
+            skip_empty: bool,
+            /// If the logs' file paths have different roots, remove the differing prefixes before partitioning
+            #[arg(long)]
+            align_roots: bool,
+            /// Match the first log's entries without this path prefix (output files keep the original paths)
+            #[arg(long, value_name = "PREFIX")]
+            root1: Option<String>,
+            /// Match the second log's entries without this path prefix (output files keep the original paths)
+            #[arg(long, value_name = "PREFIX")]
+            root2: Option<String>,
+            /// Match the first log's entries with the path prefix FROM replaced by TO (output files keep the original paths)
+            #[arg(long, num_args = 2, value_names = ["FROM", "TO"], conflicts_with = "root1")]
+            replace_prefix1: Option<Vec<String>>,
+            /// Match the second log's entries with the path prefix FROM replaced by TO (output files keep the original paths)
+            #[arg(long, num_args = 2, value_names = ["FROM", "TO"], conflicts_with = "root2")]
+            replace_prefix2: Option<Vec<String>>,
+            /// Also write all of the results to a single JSON document at this path
+            #[arg(long, value_name = "PATH")]
+            json: Option<String>,
+            /// Also print a list of the changes between the logs (A created, D deleted, M modified, R moved/renamed, ? ambiguous)
+            #[arg(long)]
+            summary: bool,
+            /// List at most N changes in the summary
+            #[arg(long, value_name = "N", requires = "summary")]
+            summary_limit: Option<usize>,
+            /// Skip the first N changes in the summary (e.g.: to show the next page with --summary-limit)
+            #[arg(long, value_name = "N", requires = "summary")]
+            summary_offset: Option<usize>,
+            /// Exit with code 0 whenever partitioning succeeds, even if the logs differ (the behavior of earlier versions)
+            #[arg(long)]
+            exit_zero: bool,
+        },
+        #[command(after_long_help = help::help_audit_string())]
+        #[command(long_about = help::long_about_audit_string())]
+        /// Partition contents of a hashdeep log against a directory into category files
+        Audit {
+            #[arg(hide_long_help = true, id="path/to/log.txt")]
+            input_file: String,
+            #[arg(hide_long_help = true, id="path/to/target_dir")]
+            target_directory: String,
             #[arg(hide_long_help = true, id="path/to/output_file_base")]
             output_file_base: String,
+            /// Compare paths relative to the target directory (e.g.: ./dir/file), as logged by `hash --relative`
+            #[arg(long)]
+            relative: bool,
+            /// Don't read files whose size and path don't appear in the log
+            #[arg(long)]
+            skip_unmatchable: bool,
+            /// The number of files to hash in parallel [default: the number of CPUs]
+            #[arg(long, value_name = "N")]
+            jobs: Option<NonZeroUsize>,
+            /// The maximum number of files to read at the same time [default: same as --jobs]
+            #[arg(long, value_name = "N")]
+            max_reads: Option<NonZeroUsize>,
+        },
+        #[command(after_long_help = help::help_verify_string())]
+        #[command(long_about = help::long_about_verify_string())]
+        /// Rehash a random sample of a hashdeep log's files to check them
+        #[command(group = clap::ArgGroup::new("sample size").required(true).args(["percent", "bytes"]))]
+        Verify {
+            #[arg(hide_long_help = true, id="path/to/log.txt")]
+            input_file: String,
+            /// Sample this percentage of the log's entries (greater than 0, at most 100)
+            #[arg(long, value_name = "P", value_parser = verify::parse_percent)]
+            percent: Option<f64>,
+            /// Sample files up to this total size, in bytes (or with a K, M, G or T suffix)
+            #[arg(long, value_name = "SIZE", value_parser = common::parse_byte_count)]
+            bytes: Option<u64>,
+            /// The random seed that selects the sample [default: based on the current time]
+            #[arg(long, value_name = "N")]
+            seed: Option<u64>,
+            /// The directory that relative logged paths are resolved from [default: the current directory]
+            #[arg(long, value_name = "DIR")]
+            base_dir: Option<String>,
+            /// The number of files to hash in parallel [default: the number of CPUs]
+            #[arg(long, value_name = "N")]
+            jobs: Option<NonZeroUsize>,
+            /// The maximum number of files to read at the same time [default: same as --jobs]
+            #[arg(long, value_name = "N")]
+            max_reads: Option<NonZeroUsize>,
         },
     }
 
     let cli_args = CliArgs::try_parse_from(args)?;
 
     match cli_args.command {
-        Commands::Hash {target_directory, output_path_base} => {
-            command::run_hashdeep_command(
-                target_directory.as_str(),
-                output_path_base.as_str(),
-                "hashdeep")?;
+        Commands::Hash {target_directory, output_path_base, engine, relative, algorithms, jobs, max_reads, save_mtimes, previous, file_types, hashdeep_binary} => {
+
+            let engine_conflict = |message: &str| -> Box<dyn Error> {
+                let mut command = CliArgs::command();
+                command.build();
+                command.find_subcommand_mut("hash")
+                    .expect("hash subcommand should exist")
+                    .error(clap::error::ErrorKind::ArgumentConflict, message)
+                    .into()
+            };
+
+            let result = match engine {
+                HashEngine::Hashdeep => {
+                    if max_reads.is_some() || save_mtimes || previous.is_some() {
+                        return Err(engine_conflict("--max-reads, --save-mtimes and --previous can only be used with '--engine native'"));
+                    }
+                    let default_options = command::HashdeepOptions::default();
+                    let options = command::HashdeepOptions {
+                        binary: hashdeep_binary.unwrap_or(default_options.binary),
+                        algorithms: algorithms.unwrap_or(default_options.algorithms),
+                        jobs,
+                        file_types: file_types.unwrap_or(default_options.file_types),
+                        relative_paths: relative,
+                    };
+                    command::run_hashdeep_command(
+                        target_directory.as_str(),
+                        output_path_base.as_str(),
+                        &options)
+                },
+                HashEngine::Native => {
+                    if file_types.is_some() || hashdeep_binary.is_some() {
+                        return Err(engine_conflict("--file-types and --hashdeep-binary can only be used with '--engine hashdeep'"));
+                    }
+                    let default_options = native_hash::NativeHashOptions::default();
+                    let jobs = jobs.unwrap_or(default_options.jobs);
+                    let options = native_hash::NativeHashOptions {
+                        algorithms: algorithms.unwrap_or(default_options.algorithms),
+                        jobs,
+                        max_concurrent_reads: max_reads.unwrap_or(jobs),
+                        relative_paths: relative,
+                        save_mtimes,
+                        previous_log: previous,
+                    };
+                    native_hash::run_native_hash_command(
+                        target_directory.as_str(),
+                        output_path_base.as_str(),
+                        &options)
+                },
+            };
+
+            //summarize the error file, even if hashdeep failed
+            let error_summary = match &result {
+                Ok(error_summary) => Some(error_summary),
+                Err(command::RunHashdeepCommandError::HashdeepFailed(_, error_summary)) => Some(error_summary),
+                Err(_) => None,
+            };
+            if let Some(lines) = error_summary.and_then(|x| x.summary_lines()) {
+                write_lines(stderr, lines)?;
+            }
+            result?;
         },
-        Commands::Sort {input_file, output_file} => {
+        Commands::HashArchive {archive, output_path, relative, algorithms} => {
+            let default_options = archive_hash::ArchiveHashOptions::default();
+            let options = archive_hash::ArchiveHashOptions {
+                algorithms: algorithms.unwrap_or(default_options.algorithms),
+                relative_paths: relative,
+            };
+            archive_hash::run_archive_hash_command(
+                archive.as_str(),
+                output_path.as_str(),
+                &options)?;
+        },
+        Commands::Sort {input_file, output_file, key, reverse, memory_limit, temp_dir} => {
+            let key = match key {
+                SortKey::Name => sort::SortKey::Name,
+                SortKey::Hashes => sort::SortKey::Hashes,
+                SortKey::Size => sort::SortKey::Size,
+                SortKey::Path => sort::SortKey::Path,
+                SortKey::Natural => sort::SortKey::Natural,
+            };
+            let default_options = sort::SortOptions::default();
+            let options = sort::SortOptions {
+                order: sort::SortOrder{key, reverse},
+                memory_budget: memory_limit.map_or(default_options.memory_budget, |x| usize::try_from(x).unwrap_or(usize::MAX)),
+                temp_directory: temp_dir,
+            };
             let warning_lines = sort::sort_log(
                 input_file.as_str(),
-                output_file.as_str()
+                output_file.as_str(),
+                &options,
             )?;
             print_hashdeep_log_warnings(input_file.as_str(), warning_lines, stderr)?;
         },
-        Commands::Root {input_file, output_file, file_path_prefix} => {
+        Commands::Root {input_file, output_file, file_path_prefix, raw} => {
             let success = root::change_root(
                 input_file.as_str(),
                 output_file.as_str(),
                 file_path_prefix.as_str(),
+                root::ChangeRootOptions{raw},
+            )?;
+            write_lines(stdout, success.info_lines)?;
+            write_lines(stderr, success.warning_lines)?;
+            print_hashdeep_log_warnings(input_file.as_str(), success.file_warning_lines, stderr)?;
+        },
+        Commands::Rewrite {input_file, output_file, rules, rules_file, drop_unmatched} => {
+            let mut rules = rewrite::parse_rules(&rules)?;
+            if let Some(rules_file) = rules_file {
+                rules.extend(rewrite::read_rules_file(rules_file.as_str())?);
+            }
+
+            let success = rewrite::rewrite_paths(
+                input_file.as_str(),
+                output_file.as_str(),
+                &rules,
+                rewrite::RewriteOptions{drop_unmatched},
             )?;
             write_lines(stdout, success.info_lines)?;
             write_lines(stderr, success.warning_lines)?;
             print_hashdeep_log_warnings(input_file.as_str(), success.file_warning_lines, stderr)?;
         },
-        Commands::Part {input_file1, input_file2, output_file_base} => {
+        Commands::Part {
+            input_file1, input_file2, output_file_base, output_dir, force, timestamped, skip_empty,
+            align_roots, root1, root2, replace_prefix1, replace_prefix2, json, summary, summary_limit, summary_offset, exit_zero,
+        } => {
+            let prefix_rewrite = |root: Option<String>, replace_prefix: Option<Vec<String>>| {
+                match (root, replace_prefix.as_deref()) {
+                    (Some(root), _) => Some(partition::PrefixRewrite{from: root.into_bytes(), to: vec![]}),
+                    (None, Some([from, to])) => Some(partition::PrefixRewrite{from: from.clone().into_bytes(), to: to.clone().into_bytes()}),
+                    _ => None,
+                }
+            };
+            let options = partition::PartitionOptions {
+                align_roots,
+                prefix_rewrite1: prefix_rewrite(root1, replace_prefix1),
+                prefix_rewrite2: prefix_rewrite(root2, replace_prefix2),
+                json_report: json,
+                change_summary: summary.then_some(change_summary::ChangeSummaryOptions {
+                    offset: summary_offset.unwrap_or_default(),
+                    limit: summary_limit,
+                }),
+                skip_empty,
+            };
+            let output = match (output_dir, output_file_base) {
+                (Some(output_dir), _) => partition::PartitionOutput::Directory(output_dir, match (force, timestamped) {
+                    (true, _) => partition::ExistingOutputPolicy::Overwrite,
+                    (_, true) => partition::ExistingOutputPolicy::TimestampedSubdirectory,
+                    _ => partition::ExistingOutputPolicy::Fail,
+                }),
+                //clap requires one of them
+                (None, output_file_base) => partition::PartitionOutput::FileBase(output_file_base.unwrap_or_default()),
+            };
+
             let partition_stats =
             partition::partition_log(
                 input_file1.as_str(),
                 input_file2.as_str(),
-                output_file_base.as_str()
+                &output,
+                &options,
             )?;
 
+            if let Some(output_directory) = &partition_stats.output_directory {
+                writeln!(stdout, "Output files were written to: {output_directory}")?;
+            }
+            write_lines(stdout, partition_stats.info_lines)?;
+
+            if let Some(root_alignment) = &partition_stats.root_alignment {
+                write_lines(stdout, root_alignment.report_lines())?;
+            }
             writeln!(stdout, "{}", partition_stats.stats_string)?;
+            if let Some(change_summary_lines) = partition_stats.change_summary_lines {
+                write_lines(stdout, change_summary_lines)?;
+            }
             print_hashdeep_log_warnings(input_file1.as_str(), partition_stats.file1_warning_lines, stderr)?;
             print_hashdeep_log_warnings(input_file2.as_str(), partition_stats.file2_warning_lines, stderr)?;
+
+            match partition_stats.outcome {
+                _ if exit_zero => {},
+                partition::PartitionOutcome::Identical => {},
+                partition::PartitionOutcome::Differences => return Ok(DIFFERENCES_FOUND_EXIT_CODE),
+                partition::PartitionOutcome::Anomalies => return Ok(ANOMALIES_FOUND_EXIT_CODE),
+            }
+        },
+        Commands::Audit {input_file, target_directory, output_file_base, relative, skip_unmatchable, jobs, max_reads} => {
+            let default_options = audit::AuditOptions::default();
+            let jobs = jobs.unwrap_or(default_options.jobs);
+            let options = audit::AuditOptions {
+                jobs,
+                max_concurrent_reads: max_reads.unwrap_or(jobs),
+                relative_paths: relative,
+                skip_unmatchable,
+            };
+
+            let audit_success = audit::audit_directory(
+                input_file.as_str(),
+                target_directory.as_str(),
+                output_file_base.as_str(),
+                options,
+            )?;
+
+            writeln!(stdout, "{}", audit_success.stats_string)?;
+            if let Some(lines) = audit_success.error_summary.summary_lines() {
+                write_lines(stderr, lines)?;
+            }
+            print_hashdeep_log_warnings(input_file.as_str(), audit_success.log_warning_lines, stderr)?;
+        },
+        Commands::Verify {input_file, percent, bytes, seed, base_dir, jobs, max_reads} => {
+            let sample_size = match (percent, bytes) {
+                (Some(percent), _) => verify::SampleSize::Percent(percent),
+                (None, Some(bytes)) => verify::SampleSize::Bytes(bytes),
+                (None, None) => unreachable!("clap should require a sample size"),
+            };
+            let jobs = jobs.unwrap_or(native_hash::NativeHashOptions::default().jobs);
+            let options = verify::VerifyOptions {
+                sample_size,
+                seed: seed.unwrap_or_else(verify::seed_from_time),
+                base_directory: base_dir,
+                jobs,
+                max_concurrent_reads: max_reads.unwrap_or(jobs),
+            };
+
+            let verify_success = verify::verify_log(input_file.as_str(), &options)?;
+            let problems_found = verify_success.problems_found();
+
+            writeln!(stdout, "{}", verify_success.stats_string)?;
+            write_lines(stdout, verify_success.problem_lines)?;
+            print_hashdeep_log_warnings(input_file.as_str(), verify_success.log_warning_lines, stderr)?;
+
+            if problems_found {
+                return Ok(DIFFERENCES_FOUND_EXIT_CODE);
+            }
         },
         Commands::Version => {
             writeln!(stdout, "hashdeep-compare version {VERSION}")?;
         }
     }
 
-    Ok(())
+    Ok(0)
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+//! Modification time files: the sidecar files that allow `hash` to reuse a previous log's entries.
+//!
+//! A modification time file accompanies a hash log (at the log's path + ".mtimes"), and records the
+//! modification time each file had when it was hashed. The log itself stays a plain hashdeep log.
+//!
+//! Format: a version line, then 1 line per log entry:
+//!
+//! >`%%%% hashdeep-compare mtimes 1`
+//! >
+//! >nanoseconds since the Unix epoch + "," + filename (the exact bytes of the log entry's filename)
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io::{BufRead, BufReader, Write};
+use std::time::UNIX_EPOCH;
+
+const VERSION_LINE: &[u8] = b"%%%% hashdeep-compare mtimes 1";
+
+/// Gets the path of the modification time file that accompanies a hash log.
+pub fn mtimes_file_path(log_path: &str) -> String {
+    format!("{log_path}.mtimes")
+}
+
+/// Gets a file's modification time in nanoseconds since the Unix epoch,
+/// or None if it isn't available (or is before the epoch).
+pub fn mtime(metadata: &Metadata) -> Option<u128> {
+    metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok().map(|x| x.as_nanos())
+}
+
+/// Writes a modification time file's version line.
+pub fn write_version_line(w: &mut impl Write) -> std::io::Result<()> {
+    w.write_all(VERSION_LINE)?;
+    w.write_all(b"\n")
+}
+
+/// Writes a modification time file entry line.
+pub fn write_mtime_line(w: &mut impl Write, mtime: u128, filename: &[u8]) -> std::io::Result<()> {
+    write!(w, "{mtime},")?;
+    w.write_all(filename)?;
+    w.write_all(b"\n")
+}
+
+/// Reads a modification time file, as a map of filenames to modification times.
+///
+/// Lines that can't be read are skipped (the files they refer to will be rehashed).
+///
+/// # Errors
+///
+/// Any error encountered while reading the file will be returned. An `InvalidData` error will be
+/// returned if the file doesn't start with the expected version line.
+pub fn read_mtimes_file(path: &str) -> std::io::Result<HashMap<Vec<u8>, u128>> {
+
+    let mut lines = BufReader::new(std::fs::File::open(path)?).split(b'\n');
+
+    match lines.next().transpose()? {
+        Some(line) if line == VERSION_LINE => {},
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a modification time file")),
+    }
+
+    let mut mtimes = HashMap::new();
+    for line in lines {
+        let line = line?;
+
+        let Some(separator) = line.iter().position(|&c| c == b',') else {continue};
+        let Some(mtime) = std::str::from_utf8(&line[..separator]).ok().and_then(|x| x.parse().ok()) else {continue};
+
+        mtimes.insert(line[separator + 1..].to_vec(), mtime);
+    }
+
+    Ok(mtimes)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_mtimes_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog.mtimes");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        let mut contents = Vec::new();
+        write_version_line(&mut contents).unwrap();
+        write_mtime_line(&mut contents, 1_700_000_000_123_456_789, b"dir/a,b.txt").unwrap();
+        write_mtime_line(&mut contents, 0, b"\xff\xfe").unwrap();
+        contents.extend(b"garbage\n-1,negative\n");
+        std::fs::write(&temp_file, &contents).unwrap();
+
+        let mtimes = read_mtimes_file(temp_file_path_str).unwrap();
+        assert_eq!(mtimes.len(), 2);
+        assert_eq!(mtimes[b"dir/a,b.txt".as_slice()], 1_700_000_000_123_456_789);
+        assert_eq!(mtimes[b"\xff\xfe".as_slice()], 0);
+
+        std::fs::write(&temp_file, "1,file\n").unwrap();
+        assert_eq!(read_mtimes_file(temp_file_path_str).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,655 @@
+//! A built-in alternative to the external hashdeep binary, for systems where it isn't available.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sha2::digest::DynDigest;
+use walkdir::WalkDir;
+
+use crate::command::{self, path_bytes, RunHashdeepCommandError};
+use crate::common;
+use crate::hash_errors::{HashError, HashErrorSummary};
+use crate::digest::{Digest, HashAlgorithm};
+use crate::log_entry::LogEntry;
+use crate::mtimes;
+use crate::partial_output::PartialFile;
+use crate::sort;
+
+/// Settings for the built-in hashing engine
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NativeHashOptions {
+    /// The hash algorithms to record, in log column order
+    pub algorithms: Vec<HashAlgorithm>,
+    /// The number of worker threads that hash files
+    pub jobs: NonZeroUsize,
+    /// The maximum number of files that may be read at the same time (across all workers)
+    pub max_concurrent_reads: NonZeroUsize,
+    /// If true, logged paths are relative to the target directory (e.g.: "./dir/file"), like hashdeep's
+    /// when run from inside it
+    pub relative_paths: bool,
+    /// If true, a modification time file is written with the log (see `previous_log`)
+    pub save_mtimes: bool,
+    /// A previous log (with a modification time file) whose entries are reused for files with
+    /// unchanged sizes and modification times, instead of rehashing them (implies `save_mtimes`)
+    pub previous_log: Option<String>,
+}
+
+impl Default for NativeHashOptions {
+
+    /// Uses hashdeep's default algorithms, and 1 worker per available CPU,
+    /// with no additional limit on concurrent reads.
+    fn default() -> Self {
+        let jobs = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+        NativeHashOptions{algorithms: HashAlgorithm::DEFAULT_COLUMNS.to_vec(), jobs, max_concurrent_reads: jobs,
+                          relative_paths: false, save_mtimes: false, previous_log: None}
+    }
+}
+
+/// Generates a hash log with the built-in hashing engine.
+///
+/// The log is equivalent to one from `run_hashdeep_command`: it includes (recursively) all regular
+/// files in `target_directory`, has the same 5-line header and entry format, and is written to
+/// `output_path_base`, with errors written to `output_path_base` + ".errors".
+/// As with `run_hashdeep_command`, these files only appear once they are complete.
+///
+/// Files are hashed in parallel (as specified by `options`), but unlike hashdeep, entries are
+/// written in a consistent order: sorted by file path, as by the `sort` subcommand.
+///
+/// With `options.save_mtimes`, each file's modification time is written to a modification time file
+/// (at `output_path_base` + ".mtimes"). With `options.previous_log`, files with the same size and
+/// modification time as in the previous log's modification time file aren't read again: their
+/// previous entries are reused. (The previous log's paths must be logged in the same form, i.e.:
+/// the same `target_directory` or `relative_paths` setting.)
+///
+/// With `options.relative_paths`, logged paths are relative to `target_directory` (e.g.: "./dir/file"),
+/// and the log header records the absolute target directory and the host name.
+///
+/// # Errors
+///
+/// An error will be returned if
+/// * with `options.relative_paths`, `target_directory` is not a directory
+/// * `options.previous_log` or its modification time file can't be read
+/// * the output log file or error file (or modification time file) already exist (will not overwrite existing files)
+/// * any other error occurs while creating or writing the output files
+///
+/// Errors that occur while reading the target files are written to the error file instead,
+/// and summarized in the returned `HashErrorSummary`.
+pub fn run_native_hash_command(
+    target_directory: &str,
+    output_path_base: &str,
+    options: &NativeHashOptions,
+) -> Result<HashErrorSummary, RunHashdeepCommandError> {
+
+    let relative_root = match options.relative_paths {
+        true => Some(command::absolute_target_directory(target_directory)?),
+        false => None,
+    };
+
+    let save_mtimes = options.save_mtimes || options.previous_log.is_some();
+
+    let previous_entries = match &options.previous_log {
+        Some(previous_log) => Some(read_previous_entries(previous_log, &options.algorithms)?),
+        None => None,
+    };
+
+    let output_files = command::open_output_files(output_path_base)?;
+
+    let mtimes_output = match save_mtimes {
+        true => {
+            let mtimes_file_path = mtimes::mtimes_file_path(output_path_base);
+            Some(PartialFile::create(&mtimes_file_path).map_err(|(e, path)| RunHashdeepCommandError::new(e, &path))?)
+        },
+        false => None,
+    };
+
+    let mut error_summary = HashErrorSummary::new(command::error_file_path(output_path_base));
+
+    let mut output_file = BufWriter::new(&output_files.output_file);
+    let mut error_file = BufWriter::new(&output_files.error_file);
+
+    write_header(&mut output_file, target_directory, output_path_base, options, relative_root.as_deref())?;
+
+    let directory_hashes = hash_directory(target_directory, options, previous_entries.as_ref(), None);
+
+    for (path, e) in directory_hashes.errors {
+        write_error(&mut error_file, &mut error_summary, path, &e)?;
+    }
+
+    for log_entry in &directory_hashes.log_entries {
+        log_entry.write_to(&mut output_file)?;
+        output_file.write_all(b"\n")?;
+    }
+
+    output_file.flush()?;
+    error_file.flush()?;
+    drop((output_file, error_file));
+
+    //the modification time file is committed first: the log only appears when its files are complete
+    if let Some((partial_mtimes_file, mtimes_file)) = mtimes_output {
+        let mut mtimes_file = BufWriter::new(mtimes_file);
+        mtimes::write_version_line(&mut mtimes_file)?;
+        for log_entry in &directory_hashes.log_entries {
+            if let Some(mtime) = directory_hashes.mtimes.get(&log_entry.filename) {
+                mtimes::write_mtime_line(&mut mtimes_file, *mtime, &log_entry.filename)?;
+            }
+        }
+        mtimes_file.flush()?;
+        drop(mtimes_file);
+        partial_mtimes_file.commit()?;
+    }
+
+    output_files.commit()?;
+    Ok(error_summary)
+}
+
+/// The results of hashing the regular files in a directory
+pub(crate) struct DirectoryHashes {
+    /// Log entries, sorted by filename
+    pub log_entries: Vec<LogEntry>,
+    /// Paths (as logged) that couldn't be hashed, with their errors, in the order they were found
+    pub errors: Vec<(Vec<u8>, std::io::Error)>,
+    /// The modification times of the files in `log_entries` (where available)
+    pub mtimes: HashMap<Vec<u8>, u128>,
+    /// Files that weren't read because of the `skip` filter: (path (as logged), size), in walk order
+    pub skipped: Vec<(Vec<u8>, u64)>,
+}
+
+/// A filter on files found by `hash_directory`: (path (as logged), size) -> true if the file should be skipped
+pub(crate) type SkipFilter<'a> = &'a dyn Fn(&[u8], u64) -> bool;
+
+/// Hashes (recursively) all regular files in `target_directory`, as specified by `options`.
+///
+/// Paths are logged as found in `target_directory`, or relative to it (with `options.relative_paths`).
+/// Files are reused from `previous_entries` (see `read_previous_entries`) where their sizes and
+/// modification times are unchanged. Files for which `skip` returns true (given the logged path and
+/// the file size) aren't read.
+pub(crate) fn hash_directory(
+    target_directory: &str,
+    options: &NativeHashOptions,
+    previous_entries: Option<&HashMap<Vec<u8>, (u128, LogEntry)>>,
+    skip: Option<SkipFilter>,
+) -> DirectoryHashes {
+
+    //the path written to the log (and error file) for a path found in the target directory
+    let logged_path = |path: &Path| -> Vec<u8> {
+        match (options.relative_paths, path.strip_prefix(target_directory)) {
+            (true, Ok(relative_path)) if relative_path.as_os_str().is_empty() => b".".to_vec(),
+            (true, Ok(relative_path)) => [b"./".as_slice(), &path_bytes(relative_path)].concat(),
+            _ => path_bytes(path),
+        }
+    };
+
+    let mut directory_hashes = DirectoryHashes {
+        log_entries: vec![],
+        errors: vec![],
+        mtimes: HashMap::new(),
+        skipped: vec![],
+    };
+
+    let mut paths = Vec::<PathBuf>::new();
+
+    for dir_entry in WalkDir::new(target_directory).sort_by_file_name() {
+
+        let dir_entry = match dir_entry {
+            Ok(x) => x,
+            Err(e) => {
+                let path = e.path().unwrap_or_else(|| Path::new(target_directory));
+                directory_hashes.errors.push((logged_path(path), walk_io_error(e)));
+                continue;
+            }
+        };
+
+        //like `hashdeep -o f`, only hash regular files
+        if !dir_entry.file_type().is_file() {
+            continue;
+        }
+
+        let filename = logged_path(dir_entry.path());
+
+        let metadata = match dir_entry.metadata() {
+            Ok(x) => x,
+            Err(e) => {
+                directory_hashes.errors.push((filename, walk_io_error(e)));
+                continue;
+            }
+        };
+
+        if skip.is_some_and(|skip| skip(&filename, metadata.len())) {
+            directory_hashes.skipped.push((filename, metadata.len()));
+            continue;
+        }
+
+        if let Some(mtime) = mtimes::mtime(&metadata) {
+            //reuse the previous log's entry, if the file's size and modification time are unchanged
+            let reused = previous_entries
+                .and_then(|x| x.get(&filename))
+                .filter(|(previous_mtime, previous_entry)| *previous_mtime == mtime && previous_entry.size == metadata.len())
+                .map(|(_, previous_entry)| previous_entry.clone());
+
+            directory_hashes.mtimes.insert(filename, mtime);
+
+            if let Some(previous_entry) = reused {
+                directory_hashes.log_entries.push(previous_entry);
+                continue;
+            }
+        }
+
+        paths.push(dir_entry.into_path());
+    }
+
+    let (hashed_entries, read_errors) = hash_files(&paths, options);
+
+    for (path, e) in read_errors {
+        let filename = logged_path(path);
+        directory_hashes.mtimes.remove(&filename);
+        directory_hashes.errors.push((filename, e));
+    }
+
+    directory_hashes.log_entries.extend(hashed_entries.into_iter()
+        .map(|(path, log_entry)| LogEntry{filename: logged_path(path), ..log_entry}));
+
+    sort::sort_entries(&mut directory_hashes.log_entries);
+    directory_hashes
+}
+
+/// Reads a previous log and its modification time file, as a map of filenames to
+/// (modification time, log entry with digests for `algorithms`).
+///
+/// Entries without a recorded modification time are omitted. If the previous log doesn't have all
+/// of `algorithms`, the map is empty (no entries can be reused).
+///
+/// # Errors
+///
+/// An error will be returned if either file can't be read.
+fn read_previous_entries(
+    previous_log: &str,
+    algorithms: &[HashAlgorithm],
+) -> Result<HashMap<Vec<u8>, (u128, LogEntry)>, RunHashdeepCommandError> {
+
+    let log_file = common::read_log_entries_from_file::<Vec<LogEntry>>(previous_log)?;
+
+    let mtimes_file_path = mtimes::mtimes_file_path(previous_log);
+    let mut mtimes = mtimes::read_mtimes_file(&mtimes_file_path)
+        .map_err(|e| RunHashdeepCommandError::PreviousMtimesFile(mtimes_file_path, e))?;
+
+    if !algorithms.iter().all(|x| log_file.hash_columns.contains(x)) {
+        return Ok(HashMap::new());
+    }
+
+    Ok(log_file.entries.into_iter()
+        .filter_map(|log_entry| {
+            let mtime = mtimes.remove(&log_entry.filename)?;
+            let digests = algorithms.iter().map(|&x| log_entry.digest(x).copied()).collect::<Option<Vec<_>>>()?;
+            Some((log_entry.filename.clone(), (mtime, LogEntry{digests, ..log_entry})))
+        })
+        .collect())
+}
+
+/// The log entries and read errors from `hash_files`, with the paths they came from
+type HashResults<'a> = (Vec<(&'a Path, LogEntry)>, Vec<(&'a Path, std::io::Error)>);
+
+/// Hashes files in parallel, returning their log entries and any read errors (in `paths` order).
+fn hash_files<'a>(paths: &'a [PathBuf], options: &NativeHashOptions) -> HashResults<'a> {
+
+    let mut log_entries = Vec::with_capacity(paths.len());
+    let mut read_errors = vec![];
+    for (path, result) in paths.iter().zip(hash_files_in_order(paths, options)) {
+        match result {
+            Ok(log_entry) => log_entries.push((path.as_path(), log_entry)),
+            Err(e) => read_errors.push((path.as_path(), e)),
+        }
+    }
+    (log_entries, read_errors)
+}
+
+/// Hashes files in parallel, as specified by `options`, returning 1 result per path (in `paths` order).
+///
+/// (`options.relative_paths` is ignored: entry filenames are the paths as given.)
+pub(crate) fn hash_files_in_order(paths: &[PathBuf], options: &NativeHashOptions) -> Vec<std::io::Result<LogEntry>> {
+
+    let next_index = AtomicUsize::new(0);
+    let read_permits = Semaphore::new(options.max_concurrent_reads.get());
+    let results = Mutex::new(Vec::<(usize, std::io::Result<LogEntry>)>::with_capacity(paths.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.get() {
+            scope.spawn(|| {
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(index) else {break};
+
+                    let result = {
+                        let _permit = read_permits.acquire();
+                        hash_file(path, &options.algorithms)
+                    };
+                    results.lock().expect("hashing threads should not panic").push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().expect("hashing threads should not panic");
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// A counting semaphore, to limit how many threads can do something at the same time
+struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+
+    fn new(permits: usize) -> Self {
+        Semaphore{available: Mutex::new(permits), released: Condvar::new()}
+    }
+
+    /// Waits until a permit is available, and takes it until the returned guard is dropped.
+    fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut available = self.available.lock().expect("semaphore users should not panic");
+        while *available == 0 {
+            available = self.released.wait(available).expect("semaphore users should not panic");
+        }
+        *available -= 1;
+        SemaphorePermit{semaphore: self}
+    }
+}
+
+struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.available.lock().expect("semaphore users should not panic") += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+fn write_header(
+    file: &mut impl Write,
+    target_directory: &str,
+    output_path_base: &str,
+    options: &NativeHashOptions,
+    relative_root: Option<&Path>,
+) -> std::io::Result<()> {
+
+    writeln!(file, "%%%% HASHDEEP-1.0")?;
+    writeln!(file, "{}", HashAlgorithm::format_line(&options.algorithms))?;
+
+    match relative_root {
+        Some(relative_root) => file.write_all(&command::relative_invocation_line(relative_root))?,
+        None => {
+            file.write_all(b"## Invoked from: ")?;
+            file.write_all(&path_bytes(&std::env::current_dir()?))?;
+        },
+    }
+    writeln!(file)?;
+
+    let mut command_line = "hashdeep-compare hash --engine native".to_string();
+    if options.algorithms != HashAlgorithm::DEFAULT_COLUMNS {
+        command_line += &format!(" --algorithms {}", HashAlgorithm::names(&options.algorithms));
+    }
+    if options.relative_paths {
+        command_line += " --relative";
+    }
+    match &options.previous_log {
+        Some(previous_log) => command_line += &format!(" --previous {previous_log}"),
+        None if options.save_mtimes => command_line += " --save-mtimes",
+        None => {},
+    }
+    writeln!(file, "## $ {command_line} {target_directory} {output_path_base}")?;
+
+    writeln!(file, "## ")?;
+    Ok(())
+}
+
+/// Gets the I/O error from a directory walk error (or converts it to one).
+fn walk_io_error(e: walkdir::Error) -> std::io::Error {
+    match e.io_error() {
+        Some(_) => e.into_io_error().expect("walk error should have an I/O error"),
+        None => std::io::Error::other(e.to_string()),
+    }
+}
+
+fn write_error(file: &mut impl Write, error_summary: &mut HashErrorSummary, path: Vec<u8>, e: &std::io::Error) -> std::io::Result<()> {
+
+    file.write_all(&path)?;
+    writeln!(file, ": {e}")?;
+
+    error_summary.push(HashError{path, kind: e.into()});
+    Ok(())
+}
+
+/// Creates a hasher for an algorithm.
+fn new_hasher(algorithm: HashAlgorithm) -> Box<dyn DynDigest> {
+    match algorithm {
+        HashAlgorithm::Md5 => Box::new(md5::Md5::default()),
+        HashAlgorithm::Sha1 => Box::new(sha1::Sha1::default()),
+        HashAlgorithm::Sha256 => Box::new(sha2::Sha256::default()),
+        HashAlgorithm::Tiger => Box::new(tiger::Tiger::default()),
+        HashAlgorithm::Whirlpool => Box::new(whirlpool::Whirlpool::default()),
+    }
+}
+
+/// Reads a file and creates its log entry, with a digest for each of `algorithms` (in that order).
+fn hash_file(path: &Path, algorithms: &[HashAlgorithm]) -> std::io::Result<LogEntry> {
+
+    let (size, digests) = hash_reader(&mut File::open(path)?, algorithms)?;
+
+    Ok(LogEntry {
+        size,
+        digests,
+        filename: path_bytes(path),
+    })
+}
+
+/// Reads a stream to its end, returning its size and a digest for each of `algorithms` (in that order).
+pub(crate) fn hash_reader(reader: &mut impl Read, algorithms: &[HashAlgorithm]) -> std::io::Result<(u64, Vec<Digest>)> {
+
+    let mut hashers = algorithms.iter().map(|x| new_hasher(*x)).collect::<Vec<_>>();
+    let mut size: u64 = 0;
+
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let bytes_read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(x) => x,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let bytes = &buffer[..bytes_read];
+        hashers.iter_mut().for_each(|x| x.update(bytes));
+        size += bytes_read as u64;
+    }
+
+    let digests = algorithms.iter().zip(hashers)
+        .map(|(algorithm, hasher)| Digest::from_bytes(*algorithm, &hasher.finalize())
+            .expect("hasher output should have its algorithm's digest length"))
+        .collect();
+
+    Ok((size, digests))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hash_file_test() {
+        let path = Path::new("tests/hashdeep_target/subfolder/123.txt");
+        let log_entry = hash_file(path, &HashAlgorithm::DEFAULT_COLUMNS).unwrap();
+
+        assert_eq!(
+            log_entry.to_string(),
+            "4,ba1f2511fc30423bdbb183fe33f3dd0f,181210f8f9c779c26da1d9b2075bde0127302ee0e3fca38c9a83f5b1dd8e5d3b,tests/hashdeep_target/subfolder/123.txt"
+        );
+    }
+
+    #[test]
+    fn hash_file_algorithms_test() {
+        use HashAlgorithm::*;
+
+        //an empty file, to compare with published digests of the empty string
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("empty");
+        std::fs::write(&path, "").unwrap();
+
+        let log_entry = hash_file(&path, &[Whirlpool, Tiger, Sha1, Sha256, Md5]).unwrap();
+        let digests = log_entry.digests.iter().map(Digest::to_string).collect::<Vec<_>>();
+
+        assert_eq!(digests, [
+            "19fa61d75522a4669b44e39c1d2e1726c530232130d407f89afee0964997f7a73e83be698b288febcf88e3e03c4f0757ea8964e59b63d93708b138cc42a66eb3",
+            "3293ac630c13f0245f92bbb1766e16167a4e58492dde73f3",
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "d41d8cd98f00b204e9800998ecf8427e",
+        ]);
+    }
+
+    #[test]
+    fn run_native_hash_command_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        let error_summary = run_native_hash_command("tests/hashdeep_target", temp_file_path_str, &NativeHashOptions::default()).unwrap();
+
+        let log = std::fs::read_to_string(&temp_file).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "%%%% HASHDEEP-1.0");
+        assert_eq!(lines[1], "%%%% size,md5,sha256,filename");
+        assert_eq!(lines[4], "## ");
+        assert_eq!(lines[5], "4,ba1f2511fc30423bdbb183fe33f3dd0f,181210f8f9c779c26da1d9b2075bde0127302ee0e3fca38c9a83f5b1dd8e5d3b,tests/hashdeep_target/subfolder/123.txt");
+
+        assert_eq!(std::fs::read_to_string(format!("{temp_file_path_str}.errors")).unwrap(), "");
+        assert_eq!(error_summary.summary_lines(), None);
+    }
+
+    #[test]
+    fn run_native_hash_command_relative_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("hashlog");
+        let temp_file_path_str = temp_file.to_str().unwrap();
+
+        let options = NativeHashOptions{relative_paths: true, ..Default::default()};
+        run_native_hash_command("tests/hashdeep_target/", temp_file_path_str, &options).unwrap();
+
+        let log = std::fs::read_to_string(&temp_file).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+
+        let invoked_from = lines[2].strip_prefix("## Invoked from: ").unwrap();
+        let target_directory = std::fs::canonicalize("tests/hashdeep_target").unwrap();
+        assert!(invoked_from.starts_with(&format!("{} (host: ", target_directory.display())));
+
+        assert_eq!(lines[5], "4,ba1f2511fc30423bdbb183fe33f3dd0f,181210f8f9c779c26da1d9b2075bde0127302ee0e3fca38c9a83f5b1dd8e5d3b,./subfolder/123.txt");
+    }
+
+    #[test]
+    fn run_native_hash_command_previous_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let target_dir = temp_dir.path().join("target");
+        std::fs::create_dir(&target_dir).unwrap();
+        std::fs::write(target_dir.join("unchanged"), "1").unwrap();
+        std::fs::write(target_dir.join("changed"), "2").unwrap();
+
+        let target_dir_str = target_dir.to_str().unwrap();
+        let log1 = temp_dir.path().join("log1").to_str().unwrap().to_string();
+        let log2 = temp_dir.path().join("log2").to_str().unwrap().to_string();
+
+        let options = NativeHashOptions{save_mtimes: true, ..Default::default()};
+        run_native_hash_command(target_dir_str, &log1, &options).unwrap();
+        assert!(Path::new(&mtimes::mtimes_file_path(&log1)).exists());
+
+        //mark the previous digests, to detect which entries are reused
+        let md5_of_1 = "c4ca4238a0b923820dcc509a6f75849b";
+        let previous_log = std::fs::read_to_string(&log1).unwrap();
+        assert!(previous_log.contains(md5_of_1));
+        std::fs::write(&log1, previous_log.replace(md5_of_1, &"0".repeat(32))).unwrap();
+
+        std::fs::write(target_dir.join("changed"), "22").unwrap();
+
+        let options = NativeHashOptions{previous_log: Some(log1.clone()), ..Default::default()};
+        run_native_hash_command(target_dir_str, &log2, &options).unwrap();
+
+        let log = std::fs::read_to_string(&log2).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[5].starts_with("2,b6d767d2f8ed5d21a44b0e5886680cb9,"), "{}", lines[5]);
+        assert!(lines[6].starts_with(&format!("1,{},", "0".repeat(32))), "{}", lines[6]);
+        assert!(Path::new(&mtimes::mtimes_file_path(&log2)).exists());
+
+        //a previous log can't be used without its modification time file
+        std::fs::remove_file(mtimes::mtimes_file_path(&log2)).unwrap();
+        let log3 = temp_dir.path().join("log3").to_str().unwrap().to_string();
+        let options = NativeHashOptions{previous_log: Some(log2.clone()), ..Default::default()};
+        let err = run_native_hash_command(target_dir_str, &log3, &options).unwrap_err();
+        assert!(matches!(err, RunHashdeepCommandError::PreviousMtimesFile(..)));
+        assert!(!Path::new(&log3).exists());
+    }
+
+    #[test]
+    fn hash_files_test() {
+        let paths = [
+            "tests/hashdeep_target_nontrivial/sub2/456.txt",
+            "tests/does_not_exist.txt",
+            "tests/hashdeep_target_nontrivial/123.txt",
+            "tests/hashdeep_target_nontrivial/sub1/123.txt",
+            "tests/hashdeep_target_nontrivial/789.txt",
+        ].map(PathBuf::from);
+
+        for (jobs, max_concurrent_reads) in [(1, 1), (4, 1), (4, 2), (8, 8)] {
+            let options = NativeHashOptions {
+                algorithms: HashAlgorithm::DEFAULT_COLUMNS.to_vec(),
+                jobs: NonZeroUsize::new(jobs).unwrap(),
+                max_concurrent_reads: NonZeroUsize::new(max_concurrent_reads).unwrap(),
+                relative_paths: false,
+                save_mtimes: false,
+                previous_log: None,
+            };
+            let (log_entries, read_errors) = hash_files(&paths, &options);
+
+            //results are in input order, regardless of thread timing
+            let filenames = log_entries.iter().map(|(_, x)| x.filename.as_slice()).collect::<Vec<_>>();
+            assert_eq!(filenames, [
+                b"tests/hashdeep_target_nontrivial/sub2/456.txt".as_slice(),
+                b"tests/hashdeep_target_nontrivial/123.txt",
+                b"tests/hashdeep_target_nontrivial/sub1/123.txt",
+                b"tests/hashdeep_target_nontrivial/789.txt",
+            ]);
+            assert_eq!(read_errors.len(), 1);
+            assert_eq!(read_errors[0].0, Path::new("tests/does_not_exist.txt"));
+        }
+    }
+
+    #[test]
+    fn semaphore_test() {
+        let semaphore = Semaphore::new(2);
+        let in_use = AtomicUsize::new(0);
+        let max_in_use = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = semaphore.acquire();
+                    let n = in_use.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_use.fetch_max(n, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    in_use.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert!(max_in_use.load(Ordering::SeqCst) <= 2);
+        assert_eq!(*semaphore.available.lock().unwrap(), 2);
+    }
+}
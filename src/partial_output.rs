@@ -0,0 +1,201 @@
+//! Output files that are written under temporary names, and only moved into place when complete.
+
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+
+/// The suffix added to an output file's path while it is being written
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Partial files that currently exist, to be removed if the program is interrupted
+static ACTIVE_PARTIAL_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+static INSTALL_INTERRUPT_HANDLER: Once = Once::new();
+
+/// An output file that is being written at a temporary (".partial") path.
+///
+/// If the `PartialFile` is dropped (or the program is interrupted with Ctrl-C) before it is committed,
+/// the temporary file is removed, so an incomplete output file is never left at the final path.
+#[derive(Debug)]
+pub struct PartialFile {
+    partial_path: PathBuf,
+    final_path: PathBuf,
+    committed: bool,
+}
+
+impl PartialFile {
+
+    /// Creates a new file at `final_path` + ".partial" (will not overwrite an existing file).
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file at `final_path` or its partial file already exists, or
+    /// if any other error occurs while creating the partial file.
+    /// Errors from `AlreadyExists` include the path that exists.
+    pub fn create(final_path: &str) -> Result<(PartialFile, File), (std::io::Error, String)> {
+
+        //a path without a file name (e.g.: "" or "dir/") can't be the target of a partial file:
+        //  attempting to create it directly will fail with the appropriate error
+        if Path::new(final_path).file_name().is_none() {
+            return match OpenOptions::new().write(true).create_new(true).open(final_path) {
+                Err(e) => Err((e, final_path.to_string())),
+                Ok(_) => {
+                    let _ = std::fs::remove_file(final_path);
+                    Err((ErrorKind::InvalidInput.into(), final_path.to_string()))
+                },
+            };
+        }
+
+        if std::fs::symlink_metadata(final_path).is_ok() {
+            return Err((ErrorKind::AlreadyExists.into(), final_path.to_string()));
+        }
+
+        install_interrupt_handler();
+
+        let partial_path = format!("{final_path}{PARTIAL_SUFFIX}");
+
+        //register before creating, so an interrupt can't leave an unregistered file behind
+        let mut active = ACTIVE_PARTIAL_FILES.lock().unwrap_or_else(|e| e.into_inner());
+
+        let file = OpenOptions::new().write(true).create_new(true).open(&partial_path)
+            .map_err(|e| {
+                let path = match e.kind() {
+                    ErrorKind::AlreadyExists => partial_path.clone(),
+                    _ => final_path.to_string(),
+                };
+                (e, path)
+            })?;
+
+        active.push(PathBuf::from(&partial_path));
+
+        Ok((PartialFile{partial_path: partial_path.into(), final_path: final_path.into(), committed: false}, file))
+    }
+
+    /// The temporary path that is being written
+    pub fn partial_path(&self) -> &Path {
+        &self.partial_path
+    }
+
+    /// Moves the partial file to its final path.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error (and remove the partial file) if a file now exists at the final path,
+    /// or if the file can't be moved.
+    pub fn commit(mut self) -> std::io::Result<()> {
+
+        if std::fs::symlink_metadata(&self.final_path).is_ok() {
+            return Err(std::io::Error::new(ErrorKind::AlreadyExists,
+                format!("{} was created while its partial file was written", self.final_path.display())));
+        }
+
+        std::fs::rename(&self.partial_path, &self.final_path)?;
+        self.committed = true;
+        Ok(())
+    }
+
+    /// Moves the partial file to `path` instead of its final path, replacing any file there
+    /// (e.g.: to keep a diagnostic file from a failed run, at a path that later runs don't check).
+    ///
+    /// # Errors
+    ///
+    /// Will return an error (and remove the partial file) if the file can't be moved.
+    pub fn commit_replacing(mut self, path: &Path) -> std::io::Result<()> {
+        std::fs::rename(&self.partial_path, path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialFile {
+
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.partial_path);
+        }
+        unregister(&self.partial_path);
+    }
+}
+
+fn unregister(partial_path: &Path) {
+    ACTIVE_PARTIAL_FILES.lock().unwrap_or_else(|e| e.into_inner())
+        .retain(|x| x != partial_path);
+}
+
+/// Installs a Ctrl-C handler that removes all active partial files, then exits.
+///
+/// If another handler was already installed (e.g.: by a program using this library), it is kept.
+fn install_interrupt_handler() {
+
+    INSTALL_INTERRUPT_HANDLER.call_once(|| {
+        let _ = ctrlc::set_handler(|| {
+            let active = ACTIVE_PARTIAL_FILES.lock().unwrap_or_else(|e| e.into_inner());
+            for partial_path in active.iter() {
+                let _ = std::fs::remove_file(partial_path);
+            }
+            // 130: the conventional exit code for SIGINT
+            std::process::exit(130);
+        });
+    });
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commit_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let final_path = temp_dir.path().join("output");
+        let final_path_str = final_path.to_str().unwrap();
+
+        let (partial_file, _) = PartialFile::create(final_path_str).unwrap();
+        assert!(partial_file.partial_path().exists());
+        assert!(!final_path.exists());
+
+        partial_file.commit().unwrap();
+        assert!(final_path.exists());
+        assert!(!temp_dir.path().join("output.partial").exists());
+    }
+
+    #[test]
+    fn drop_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let final_path = temp_dir.path().join("output");
+        let final_path_str = final_path.to_str().unwrap();
+
+        let (partial_file, _) = PartialFile::create(final_path_str).unwrap();
+        let partial_path = partial_file.partial_path().to_path_buf();
+        assert!(ACTIVE_PARTIAL_FILES.lock().unwrap().contains(&partial_path));
+
+        drop(partial_file);
+        assert!(!partial_path.exists());
+        assert!(!final_path.exists());
+        assert!(!ACTIVE_PARTIAL_FILES.lock().unwrap().contains(&partial_path));
+    }
+
+    #[test]
+    fn create_errors_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let final_path = temp_dir.path().join("output");
+        let final_path_str = final_path.to_str().unwrap();
+
+        std::fs::write(&final_path, "").unwrap();
+        let (e, path) = PartialFile::create(final_path_str).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(path, final_path_str);
+
+        std::fs::remove_file(&final_path).unwrap();
+        let partial_path_str = format!("{final_path_str}.partial");
+        std::fs::write(&partial_path_str, "").unwrap();
+        let (e, path) = PartialFile::create(final_path_str).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(path, partial_path_str);
+
+        let (e, path) = PartialFile::create("").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+        assert_eq!(path, "");
+    }
+}
@@ -1,8 +1,128 @@
-use crate::common;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::change_summary::{self, ChangeSummaryOptions};
+use crate::common::{self, WriteToFileError};
+use crate::digest::HashAlgorithm;
 use crate::log_entry::LogEntry;
-use crate::partitioner;
+use crate::partitioner::{self, MatchPartition};
+use crate::partitioner::directory_move::{self, DirectoryMove};
+use crate::partition_report::{PartitionReport, ReportInput};
+use crate::rewrite::RewriteRule;
+use crate::root_alignment::{self, RootAlignment};
+
+
+#[derive(Error, Debug)]
+pub enum PartitionLogError {
+
+    #[error("the logs have no hash algorithms in common (file 1: {0}; file 2: {1})")]
+    NoSharedHashAlgorithms(String, String),
+
+    #[error("the output directory \"{0}\" cannot be created ({1})")]
+    CreateOutputDirectory(String, #[source] std::io::Error),
+
+    #[error("the existing output file \"{0}\" cannot be removed ({1})")]
+    RemoveOutputFile(String, #[source] std::io::Error),
+}
 
 
+/// Where `partition_log` writes its output files
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum PartitionOutput {
+    /// Output file paths are made by adding suffixes (e.g.: "_full_match_pairs") to a path prefix
+    /// (its directory must already exist)
+    FileBase(String),
+    /// Output files with fixed names (e.g.: "full_match_pairs") are written in a directory,
+    /// which is created if it doesn't exist
+    Directory(String, ExistingOutputPolicy),
+}
+
+/// What `partition_log` does when its output directory already has output files in it
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ExistingOutputPolicy {
+    /// Abort (checked before the logs are read, and again before any output is written)
+    #[default]
+    Fail,
+    /// Replace them (existing output files of skipped empty categories are removed too)
+    Overwrite,
+    /// Write to a new subdirectory named for the current time (UTC), e.g.: "2024-01-31_235959"
+    TimestampedSubdirectory,
+}
+
+
+/// A path prefix replacement, applied to a log's entries before they are matched
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PrefixRewrite {
+    /// The prefix to replace, matched by whole path components (as by `root`)
+    pub from: Vec<u8>,
+    /// The new prefix (if empty, the prefix is removed)
+    pub to: Vec<u8>,
+}
+
+/// Settings for `partition_log`
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PartitionOptions {
+    /// If true, a detected root difference between the logs is removed before partitioning
+    /// (see `RootAlignment`)
+    pub align_roots: bool,
+    /// Replaces a prefix of file 1's paths before matching (entries that don't start with it are unchanged)
+    pub prefix_rewrite1: Option<PrefixRewrite>,
+    /// Replaces a prefix of file 2's paths before matching (entries that don't start with it are unchanged)
+    pub prefix_rewrite2: Option<PrefixRewrite>,
+    /// If set, a JSON report of the results (see `PartitionReport`) is also written to this path
+    pub json_report: Option<String>,
+    /// If set, a list of the changes between the logs is also returned (see `change_summary_lines`)
+    pub change_summary: Option<ChangeSummaryOptions>,
+    /// If true, no output files are written for empty categories
+    pub skip_empty: bool,
+}
+
+/// What a partition found, from most to least severe
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum PartitionOutcome {
+    /// Anomalies were found: full match groups or name match groups (these imply invalid hashdeep logs)
+    Anomalies,
+    /// The logs differ: there are entries in categories other than full match pairs (but no anomalies)
+    Differences,
+    /// The logs are identical: all entries are in full match pairs
+    #[default]
+    Identical,
+}
+
+impl PartitionOutcome {
+
+    /// Gets the outcome of a partition.
+    pub fn of(mp: &MatchPartition) -> PartitionOutcome {
+        let anomalies = [
+            mp.full_match_groups.is_empty(),
+            mp.full_match_groups_file1.is_empty(),
+            mp.full_match_groups_file2.is_empty(),
+            mp.name_match_groups.is_empty(),
+            mp.name_match_groups_file1.is_empty(),
+            mp.name_match_groups_file2.is_empty(),
+        ].contains(&false);
+
+        let differences = [
+            mp.name_match_pairs.is_empty(),
+            mp.hashes_match_pairs.is_empty(),
+            mp.hashes_match_groups.is_empty(),
+            mp.hashes_match_groups_file1.is_empty(),
+            mp.hashes_match_groups_file2.is_empty(),
+            mp.no_match_file1.is_empty(),
+            mp.no_match_file2.is_empty(),
+        ].contains(&false);
+
+        match (anomalies, differences) {
+            (true, _) => PartitionOutcome::Anomalies,
+            (false, true) => PartitionOutcome::Differences,
+            (false, false) => PartitionOutcome::Identical,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
 pub struct PartitionLogSuccess
 {
@@ -10,14 +130,26 @@ pub struct PartitionLogSuccess
     pub file1_warning_lines: Option<Vec<String>>,
     /// Printable warning lines about the second hashdeep log file, if any were emitted
     pub file2_warning_lines: Option<Vec<String>>,
+    /// Printable lines about the prefix rewrites applied before matching (if any)
+    pub info_lines: Vec<String>,
     /// Printable statistics about the partitioning results
     pub stats_string: String,
+    /// The root difference detected between the logs' file paths (and whether it was removed), if any
+    pub root_alignment: Option<RootAlignment>,
+    /// Printable lines listing the changes between the logs (if `options.change_summary` was set)
+    pub change_summary_lines: Option<Vec<String>>,
+    /// Whether the logs are identical, differ, or have anomalies
+    pub outcome: PartitionOutcome,
+    /// The directory the output files were written to (for `PartitionOutput::Directory`)
+    pub output_directory: Option<String>,
 }
 
 /// Partitions entries from two hashdeep logs by content and name matches.
 ///
-/// hashdeep logs are loaded from filename1 and filename2, and output groups
-/// are based on the output_filename_base path prefix.
+/// hashdeep logs are loaded from filename1 and filename2, and output groups are written
+/// as specified by `output`: either to paths with the output_filename_base path prefix,
+/// or to files with fixed names in an output directory (see `PartitionOutput`).
+/// With `options.skip_empty`, empty groups aren't written.
 ///
 /// Entries in the loaded logs will be grouped in this order:
 ///
@@ -34,45 +166,379 @@ pub struct PartitionLogSuccess
 ///
 /// Each log entry is guaranteed to be represented in exactly one group.
 ///
+/// Directories whose files (all or most of them) moved together are also listed as directory moves,
+/// inferred from the "hashes match" pairs (see `directory_move::infer_directory_moves`).
+///
+/// File content is compared by size and by each hash algorithm that both logs recorded
+/// (according to their headers): other hash columns are ignored.
+///
+/// If most "hashes match" pairs have paths that differ only by the same prefixes (e.g.: one log was made
+/// from `/home/user` with `hashdeep -lr data/`, and the other from inside `data/`), the logs' roots probably
+/// differ: the prefixes are reported in the result, and with `options.align_roots`, they are removed from the
+/// entries that start with them (as text), and the entries are partitioned again.
+///
+/// `options.prefix_rewrite1` and `options.prefix_rewrite2` replace a prefix of each log's paths before matching.
+/// Path changes (from rewrites or root alignment) only affect matching: output files list the original entries.
+///
+/// With `options.json_report`, all of the results are also written to a single JSON document
+/// (see `partition_report`).
+///
+/// With `options.change_summary`, a compact list of the changes between the logs is also returned
+/// (see `change_summary::change_summary_lines`).
+///
 /// On success, returns a statistics string about the successful operation,
-/// plus warning strings if any were emitted while loading the hashdeep logs.
+/// plus warning strings if any were emitted while loading the hashdeep logs,
+/// and whether the logs are identical, differ, or have anomalies (see `PartitionOutcome`).
 ///
 /// # Errors
 ///
 /// Any error emitted while reading or writing the files will be returned.
 ///
+/// An error will be returned if the logs have no hash algorithms in common.
+///
 /// An integrity check is run on the partitioning results after calculation:
 ///  an error will be emitted if this fails (this is extremely unlikely).
 ///
-pub fn partition_log(filename1: &str, filename2: &str, output_filename_base: &str) -> Result<PartitionLogSuccess, Box<dyn std::error::Error>> {
+pub fn partition_log(filename1: &str, filename2: &str, output: &PartitionOutput, options: &PartitionOptions) -> Result<PartitionLogSuccess, Box<dyn std::error::Error>> {
+
+    if let Some(json_report) = options.json_report.as_ref().filter(|x| std::fs::symlink_metadata(x).is_ok()) {
+        return Err(WriteToFileError::OutputFileExists(json_report.clone()).into());
+    }
+    if let PartitionOutput::Directory(directory, ExistingOutputPolicy::Fail) = output {
+        if let Some(existing) = existing_output_files(directory).next() {
+            return Err(WriteToFileError::OutputFileExists(existing).into());
+        }
+    }
 
     let log_file1 = common::read_log_entries_from_file::<Vec<LogEntry>>(filename1)?;
     let log_file2 = common::read_log_entries_from_file::<Vec<LogEntry>>(filename2)?;
 
-    let from_file1: Vec<&LogEntry> = log_file1.entries.iter().collect::<Vec<&LogEntry>>();
-    let from_file2: Vec<&LogEntry> = log_file2.entries.iter().collect::<Vec<&LogEntry>>();
+    let hash_algorithms = shared_hash_algorithms(&log_file1.hash_columns, &log_file2.hash_columns)?;
+
+    //entries are matched by copies with rewritten paths (if any), but the originals are written
+    let mut info_lines = vec![];
+    let mut rewritten1 = rewrite_prefix(&log_file1.entries, options.prefix_rewrite1.as_ref(), 1, &mut info_lines);
+    let mut rewritten2 = rewrite_prefix(&log_file2.entries, options.prefix_rewrite2.as_ref(), 2, &mut info_lines);
+
+    let mut root_alignment = {
+        let matched1 = rewritten1.as_deref().unwrap_or(&log_file1.entries);
+        let matched2 = rewritten2.as_deref().unwrap_or(&log_file2.entries);
+        let mp = match_entries((matched1, matched1), (matched2, matched2), &hash_algorithms)?;
+        root_alignment::detect_root_alignment(&mp, &log_file1.header_lines, &log_file2.header_lines)
+    };
+
+    if let Some(root_alignment) = root_alignment.as_mut().filter(|_| options.align_roots) {
+        let rewritten1 = rewritten1.get_or_insert_with(|| log_file1.entries.clone());
+        let rewritten2 = rewritten2.get_or_insert_with(|| log_file2.entries.clone());
+        root_alignment.applied = Some((
+            root_alignment::remove_prefix(rewritten1, &root_alignment.prefix1),
+            root_alignment::remove_prefix(rewritten2, &root_alignment.prefix2),
+        ));
+    }
+
+    let matched1 = rewritten1.as_deref().unwrap_or(&log_file1.entries);
+    let matched2 = rewritten2.as_deref().unwrap_or(&log_file2.entries);
+    let mp = match_entries((matched1, &log_file1.entries), (matched2, &log_file2.entries), &hash_algorithms)?;
+
+    let directory_moves = directory_move::infer_directory_moves(&mp);
+
+    //the output is only prepared (e.g.: old output files are removed) once the logs have been read and partitioned,
+    //  so a failed run leaves any previous output as it was
+    let (output_directory, output_filenames) = prepare_output(output)?;
+
+    let stats_string = write_partition(
+        &mp, &directory_moves,
+        &hash_algorithms, &log_file1.hash_columns, &log_file2.hash_columns,
+        &output_filenames, options.skip_empty)?;
+
+    let file1_warning_lines = log_file1.warning_report();
+    let file2_warning_lines = log_file2.warning_report();
+
+    if let Some(json_report) = &options.json_report {
+        PartitionReport {
+            input1: ReportInput{path: filename1, hash_columns: &log_file1.hash_columns, warning_lines: file1_warning_lines.as_deref()},
+            input2: ReportInput{path: filename2, hash_columns: &log_file2.hash_columns, warning_lines: file2_warning_lines.as_deref()},
+            hash_algorithms: &hash_algorithms,
+            info_lines: &info_lines,
+            root_alignment: root_alignment.as_ref(),
+            mp: &mp,
+            directory_moves: &directory_moves,
+        }.write_to_file(json_report)?;
+    }
+
+    Ok(PartitionLogSuccess
+    {
+        file1_warning_lines,
+        file2_warning_lines,
+        info_lines,
+        stats_string,
+        root_alignment,
+        change_summary_lines: options.change_summary.map(|x| change_summary::change_summary_lines(&mp, x)),
+        outcome: PartitionOutcome::of(&mp),
+        output_directory,
+    })
+}
+
+/// Gets the output file paths for `output`, and for `PartitionOutput::Directory`, the directory they're in
+/// (after creating it, and applying its `ExistingOutputPolicy`).
+///
+/// # Errors
+///
+/// An error will be returned if the output directory can't be created, or if it has output files in it
+/// that can't be removed (with `ExistingOutputPolicy::Overwrite`) or aren't allowed (with `ExistingOutputPolicy::Fail`).
+fn prepare_output(output: &PartitionOutput) -> Result<(Option<String>, [String; 15]), Box<dyn std::error::Error>> {
+
+    let (directory, policy) = match output {
+        PartitionOutput::FileBase(output_filename_base) => return Ok((None, partition_output_filenames(output_filename_base))),
+        PartitionOutput::Directory(directory, policy) => (directory, *policy),
+    };
+
+    std::fs::create_dir_all(directory)
+        .map_err(|e| PartitionLogError::CreateOutputDirectory(directory.clone(), e))?;
 
-    let mp = partitioner::match_partition(&from_file1, &from_file2)?;
+    let directory = match policy {
+        ExistingOutputPolicy::TimestampedSubdirectory => create_timestamped_subdirectory(directory, SystemTime::now())?,
+        _ => directory.clone(),
+    };
 
-    common::write_match_pairs_to_file(&mp.full_match_pairs, format!("{output_filename_base}_full_match_pairs").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.full_match_groups_file1, format!("{output_filename_base}_full_match_groups_file1_only").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.full_match_groups_file2, format!("{output_filename_base}_full_match_groups_file2_only").as_str())?;
-    common::write_match_groups_to_file(&mp.full_match_groups, format!("{output_filename_base}_full_match_groups_file1_and_file2").as_str())?;
-    common::write_match_pairs_to_file(&mp.name_match_pairs, format!("{output_filename_base}_name_match_pairs").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.name_match_groups_file1, format!("{output_filename_base}_name_match_groups_file1_only").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.name_match_groups_file2, format!("{output_filename_base}_name_match_groups_file2_only").as_str())?;
-    common::write_match_groups_to_file(&mp.name_match_groups, format!("{output_filename_base}_name_match_groups_file1_and_file2").as_str())?;
-    common::write_match_pairs_to_file(&mp.hashes_match_pairs, format!("{output_filename_base}_hashes_match_pairs").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.hashes_match_groups_file1, format!("{output_filename_base}_hashes_match_groups_file1_only").as_str())?;
-    common::write_single_file_match_groups_to_file(&mp.hashes_match_groups_file2, format!("{output_filename_base}_hashes_match_groups_file2_only").as_str())?;
-    common::write_match_groups_to_file(&mp.hashes_match_groups, format!("{output_filename_base}_hashes_match_groups_file1_and_file2").as_str())?;
-    common::write_log_entries_to_file(&mp.no_match_file1, format!("{output_filename_base}_no_match_entries_file1").as_str())?;
-    common::write_log_entries_to_file(&mp.no_match_file2, format!("{output_filename_base}_no_match_entries_file2").as_str())?;
+    match policy {
+        ExistingOutputPolicy::Fail => {
+            if let Some(x) = existing_output_files(&directory).next() {
+                return Err(WriteToFileError::OutputFileExists(x).into());
+            }
+        },
+        ExistingOutputPolicy::Overwrite => {
+            for x in existing_output_files(&directory) {
+                std::fs::remove_file(&x).map_err(|e| PartitionLogError::RemoveOutputFile(x.clone(), e))?;
+            }
+        },
+        ExistingOutputPolicy::TimestampedSubdirectory => {},
+    }
+
+    let output_filenames = output_directory_filenames(&directory);
+    Ok((Some(directory), output_filenames))
+}
+
+/// Gets the output file paths in an output directory.
+fn output_directory_filenames(directory: &str) -> [String; 15] {
+    OUTPUT_CATEGORIES.map(|x| Path::new(directory).join(x).to_string_lossy().to_string())
+}
+
+/// Gets the output file paths in an output directory that already exist.
+fn existing_output_files(directory: &str) -> impl Iterator<Item = String> {
+    output_directory_filenames(directory).into_iter().filter(|x| std::fs::symlink_metadata(x).is_ok())
+}
+
+/// Gets a directory name for a time (in seconds since the Unix epoch, UTC), e.g.: "2024-01-31_235959".
+fn timestamp_directory_name(seconds: u64) -> String {
+
+    //the civil date of a day count (see: https://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+    let days = seconds / 86400;
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    let time_of_day = seconds % 86400;
+    format!("{year:04}-{month:02}-{day:02}_{:02}{:02}{:02}", time_of_day / 3600, time_of_day / 60 % 60, time_of_day % 60)
+}
+
+/// Creates a new subdirectory of `directory`, named for `time` (with a numbered suffix if that name is taken),
+/// and returns its path.
+fn create_timestamped_subdirectory(directory: &str, time: SystemTime) -> Result<String, PartitionLogError> {
+
+    let name = timestamp_directory_name(time.duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0));
+
+    let mut number = 1;
+    loop {
+        let path = match number {
+            1 => Path::new(directory).join(&name),
+            _ => Path::new(directory).join(format!("{name}_{number}")),
+        };
+        let path_string = path.to_string_lossy().to_string();
+
+        match std::fs::create_dir(&path) {
+            Ok(()) => return Ok(path_string),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => number += 1,
+            Err(e) => return Err(PartitionLogError::CreateOutputDirectory(path_string, e)),
+        }
+    }
+}
+
+/// Gets copies of `entries` with a prefix replaced (or None, if there's no `prefix_rewrite`),
+/// and adds a line about it to `info_lines`.
+fn rewrite_prefix(entries: &[LogEntry], prefix_rewrite: Option<&PrefixRewrite>, file_number: usize, info_lines: &mut Vec<String>) -> Option<Vec<LogEntry>> {
+
+    let PrefixRewrite{from, to} = prefix_rewrite?;
+    let rule = RewriteRule::ReplacePrefix{from: from.clone(), to: to.clone()};
+
+    let mut count = 0;
+    let rewritten = entries.iter().map(|x| match rule.apply(&x.filename) {
+        Some(filename) => {
+            count += 1;
+            LogEntry{filename, ..x.clone()}
+        },
+        None => x.clone(),
+    }).collect();
+
+    let from = String::from_utf8_lossy(from);
+    let rewrite = match to.as_slice() {
+        b"" => format!("without the prefix \"{from}\""),
+        to => format!("with the prefix \"{from}\" replaced by \"{}\"", String::from_utf8_lossy(to)),
+    };
+    info_lines.push(format!("File {file_number} paths were matched {rewrite} ({count} entries changed)"));
+
+    Some(rewritten)
+}
+
+/// Partitions two sets of entries: each is (the entries to match as, the entries to partition), where
+/// the entries to match as are the entries to partition, or copies of them (at the same indices) with rewritten paths.
+fn match_entries<'a>(entries1: (&[LogEntry], &'a [LogEntry]), entries2: (&[LogEntry], &'a [LogEntry]), hash_algorithms: &[HashAlgorithm])
+    -> Result<MatchPartition<'a>, partitioner::MatchPartitionError> {
+
+    let from_file1: Vec<(&LogEntry, &LogEntry)> = entries1.0.iter().zip(entries1.1).collect();
+    let from_file2: Vec<(&LogEntry, &LogEntry)> = entries2.0.iter().zip(entries2.1).collect();
+    partitioner::match_partition_as(&from_file1, &from_file2, hash_algorithms)
+}
+
+/// Gets the hash algorithms that both logs recorded (in file 1's order).
+///
+/// # Errors
+///
+/// An error will be returned if the logs have no hash algorithms in common.
+fn shared_hash_algorithms(hash_columns1: &[HashAlgorithm], hash_columns2: &[HashAlgorithm]) -> Result<Vec<HashAlgorithm>, PartitionLogError> {
+
+    let hash_algorithms: Vec<HashAlgorithm> = hash_columns1.iter()
+        .filter(|x| hash_columns2.contains(x))
+        .copied()
+        .collect();
+
+    if hash_algorithms.is_empty() {
+        return Err(PartitionLogError::NoSharedHashAlgorithms(
+            HashAlgorithm::names(hash_columns1),
+            HashAlgorithm::names(hash_columns2),
+        ));
+    }
+    Ok(hash_algorithms)
+}
+
+/// Partitions two sets of log entries (with the specified hash columns), writes the partition
+/// output files (as described for `partition_log`), and returns a statistics string.
+///
+/// # Errors
+///
+/// Any error emitted while writing the files will be returned.
+///
+/// An error will be returned if the entries have no hash algorithms in common.
+///
+/// An integrity check is run on the partitioning results after calculation:
+///  an error will be emitted if this fails (this is extremely unlikely).
+pub(crate) fn partition_entries(
+    entries1: &[LogEntry],
+    hash_columns1: &[HashAlgorithm],
+    entries2: &[LogEntry],
+    hash_columns2: &[HashAlgorithm],
+    output_filename_base: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+
+    let from_file1: Vec<&LogEntry> = entries1.iter().collect::<Vec<&LogEntry>>();
+    let from_file2: Vec<&LogEntry> = entries2.iter().collect::<Vec<&LogEntry>>();
+
+    let hash_algorithms = shared_hash_algorithms(hash_columns1, hash_columns2)?;
+
+    let mp = partitioner::match_partition(&from_file1, &from_file2, &hash_algorithms)?;
+    let directory_moves = directory_move::infer_directory_moves(&mp);
+
+    write_partition(
+        &mp, &directory_moves,
+        &hash_algorithms, hash_columns1, hash_columns2,
+        &partition_output_filenames(output_filename_base), false)
+}
+
+/// Writes the partition output files (as described for `partition_log`) to `output_filenames`
+/// (in the order of `OUTPUT_CATEGORIES`), and returns a statistics string.
+///
+/// With `skip_empty`, files for empty categories aren't written.
+///
+/// # Errors
+///
+/// Any error emitted while writing the files will be returned.
+fn write_partition(
+    mp: &MatchPartition,
+    directory_moves: &[DirectoryMove],
+    hash_algorithms: &[HashAlgorithm],
+    hash_columns1: &[HashAlgorithm],
+    hash_columns2: &[HashAlgorithm],
+    output_filenames: &[String; 15],
+    skip_empty: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+
+    let [
+        full_match_pairs,
+        full_match_groups_file1_only,
+        full_match_groups_file2_only,
+        full_match_groups_file1_and_file2,
+        name_match_pairs,
+        name_match_groups_file1_only,
+        name_match_groups_file2_only,
+        name_match_groups_file1_and_file2,
+        hashes_match_pairs,
+        directory_moves_filename,
+        hashes_match_groups_file1_only,
+        hashes_match_groups_file2_only,
+        hashes_match_groups_file1_and_file2,
+        no_match_entries_file1,
+        no_match_entries_file2,
+    ] = output_filenames;
+
+    //writes a category's file, unless it's skipped as empty
+    fn write<T>(
+        entries: &[T],
+        filename: &str,
+        skip_empty: bool,
+        write_to_file: impl FnOnce(&[T], &str) -> Result<(), WriteToFileError>,
+    ) -> Result<(), WriteToFileError> {
+        if skip_empty && entries.is_empty() {
+            return Ok(());
+        }
+        write_to_file(entries, filename)
+    }
+
+    write(&mp.full_match_pairs, full_match_pairs, skip_empty, common::write_match_pairs_to_file)?;
+    write(&mp.full_match_groups_file1, full_match_groups_file1_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.full_match_groups_file2, full_match_groups_file2_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.full_match_groups, full_match_groups_file1_and_file2, skip_empty, common::write_match_groups_to_file)?;
+    write(&mp.name_match_pairs, name_match_pairs, skip_empty, common::write_match_pairs_to_file)?;
+    write(&mp.name_match_groups_file1, name_match_groups_file1_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.name_match_groups_file2, name_match_groups_file2_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.name_match_groups, name_match_groups_file1_and_file2, skip_empty, common::write_match_groups_to_file)?;
+    write(&mp.hashes_match_pairs, hashes_match_pairs, skip_empty, common::write_match_pairs_to_file)?;
+    write(directory_moves, directory_moves_filename, skip_empty, common::write_directory_moves_to_file)?;
+    write(&mp.hashes_match_groups_file1, hashes_match_groups_file1_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.hashes_match_groups_file2, hashes_match_groups_file2_only, skip_empty, common::write_single_file_match_groups_to_file)?;
+    write(&mp.hashes_match_groups, hashes_match_groups_file1_and_file2, skip_empty, common::write_match_groups_to_file)?;
+    write(&mp.no_match_file1, no_match_entries_file1, skip_empty, |x, y| common::write_log_entries_to_file(x.iter().copied(), y))?;
+    write(&mp.no_match_file2, no_match_entries_file2, skip_empty, |x, y| common::write_log_entries_to_file(x.iter().copied(), y))?;
 
 
     let mut stats_string = String::new();
     stats_string.push_str("log partition statistics:\n");
     stats_string.push_str("   (note: \"pairs\" have 1 entry in each file)\n");
+    stats_string.push_str(format!("   (hashes compared: size,{})\n", HashAlgorithm::names(hash_algorithms)).as_str());
+    for (file_number, hash_columns) in [(1, hash_columns1), (2, hash_columns2)] {
+        let ignored: Vec<HashAlgorithm> = hash_columns.iter()
+            .filter(|x| !hash_algorithms.contains(x))
+            .copied()
+            .collect();
+        if !ignored.is_empty() {
+            stats_string.push_str(format!("   (hashes ignored: {} (file {file_number} only))\n", HashAlgorithm::names(&ignored)).as_str());
+        }
+    }
     stats_string.push_str(format!(" {} full match pairs\n", mp.full_match_pairs.len()).as_str());
     stats_string.push_str(format!(" {} full match groups in file 1 only (should be 0)\n", mp.full_match_groups_file1.len()).as_str());
     stats_string.push_str(format!(" {} full match groups in file 2 only (should be 0)\n", mp.full_match_groups_file2.len()).as_str());
@@ -82,16 +548,95 @@ pub fn partition_log(filename1: &str, filename2: &str, output_filename_base: &st
     stats_string.push_str(format!(" {} name match groups in file 2 only (should be 0)\n", mp.name_match_groups_file2.len()).as_str());
     stats_string.push_str(format!(" {} name match groups in both files (should be 0)\n", mp.name_match_groups.len()).as_str());
     stats_string.push_str(format!(" {} hashes match pairs\n", mp.hashes_match_pairs.len()).as_str());
+    stats_string.push_str(format!("   {} directory moves (of {} hashes match pairs)\n",
+        directory_moves.len(),
+        directory_moves.iter().map(|x| x.pairs.len()).sum::<usize>()).as_str());
     stats_string.push_str(format!(" {} hashes match groups in file 1 only\n", mp.hashes_match_groups_file1.len()).as_str());
     stats_string.push_str(format!(" {} hashes match groups in file 2 only\n", mp.hashes_match_groups_file2.len()).as_str());
     stats_string.push_str(format!(" {} hashes match groups in both files\n", mp.hashes_match_groups.len()).as_str());
     stats_string.push_str(format!(" {} entries in file 1 with no match\n", mp.no_match_file1.len()).as_str());
     stats_string.push_str(format!(" {} entries in file 2 with no match\n", mp.no_match_file2.len()).as_str());
 
-    Ok(PartitionLogSuccess
-    {
-        file1_warning_lines: log_file1.warning_report(),
-        file2_warning_lines: log_file2.warning_report(),
-        stats_string
-    })
-}
\ No newline at end of file
+    Ok(stats_string)
+}
+
+/// The output file name of each category (in the order they are written),
+/// as a suffix of an output file base path, or as a file name in an output directory
+const OUTPUT_CATEGORIES: [&str; 15] = [
+    "full_match_pairs",
+    "full_match_groups_file1_only",
+    "full_match_groups_file2_only",
+    "full_match_groups_file1_and_file2",
+    "name_match_pairs",
+    "name_match_groups_file1_only",
+    "name_match_groups_file2_only",
+    "name_match_groups_file1_and_file2",
+    "hashes_match_pairs",
+    "directory_moves",
+    "hashes_match_groups_file1_only",
+    "hashes_match_groups_file2_only",
+    "hashes_match_groups_file1_and_file2",
+    "no_match_entries_file1",
+    "no_match_entries_file2",
+];
+
+/// Gets the paths of the files written by `partition_entries` (in the order they are written).
+pub(crate) fn partition_output_filenames(output_filename_base: &str) -> [String; 15] {
+    OUTPUT_CATEGORIES.map(|x| format!("{output_filename_base}_{x}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamp_directory_name_test() {
+        assert_eq!(timestamp_directory_name(0), "1970-01-01_000000");
+        assert_eq!(timestamp_directory_name(951782400), "2000-02-29_000000");
+        assert_eq!(timestamp_directory_name(1700000000), "2023-11-14_221320");
+        assert_eq!(timestamp_directory_name(4102444799), "2099-12-31_235959");
+    }
+
+    #[test]
+    fn create_timestamped_subdirectory_test() {
+        let directory = tempfile::tempdir().unwrap();
+        let directory = directory.path().to_str().unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1700000000);
+
+        let first = create_timestamped_subdirectory(directory, time).unwrap();
+        let second = create_timestamped_subdirectory(directory, time).unwrap();
+        assert_eq!(first, format!("{directory}/2023-11-14_221320"));
+        assert_eq!(second, format!("{directory}/2023-11-14_221320_2"));
+        assert!(Path::new(&first).is_dir());
+        assert!(Path::new(&second).is_dir());
+
+        assert!(matches!(
+            create_timestamped_subdirectory(&format!("{directory}/nonexistent"), time),
+            Err(PartitionLogError::CreateOutputDirectory(..))));
+    }
+
+    #[test]
+    fn partition_log_failure_leaves_no_output_directory_test() {
+        let directory = tempfile::tempdir().unwrap();
+        let results = directory.path().join("results");
+        let output = PartitionOutput::Directory(results.to_str().unwrap().to_string(), ExistingOutputPolicy::TimestampedSubdirectory);
+
+        assert!(partition_log("tests/part_files/nonexistent", "tests/part_files/dir_move_file2", &output, &PartitionOptions::default()).is_err());
+        assert!(!results.exists());
+    }
+
+    #[test]
+    fn partition_log_existing_output_checked_first_test() {
+        let directory = tempfile::tempdir().unwrap();
+        let directory = directory.path().to_str().unwrap();
+        let existing = format!("{directory}/full_match_pairs");
+        std::fs::write(&existing, "old").unwrap();
+        let output = PartitionOutput::Directory(directory.to_string(), ExistingOutputPolicy::Fail);
+
+        //the existing output file is reported before the (missing) log would be read
+        let err = partition_log("tests/part_files/nonexistent", "tests/part_files/dir_move_file2", &output, &PartitionOptions::default()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<WriteToFileError>(), Some(WriteToFileError::OutputFileExists(x)) if *x == existing));
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+    }
+}
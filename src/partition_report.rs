@@ -0,0 +1,223 @@
+//! A machine-readable (JSON) report of `part` results, with every partition category in one document
+
+use serde_json::{json, Value};
+
+use crate::common::{self, WriteToFileError};
+use crate::digest::HashAlgorithm;
+use crate::log_entry::LogEntry;
+use crate::partitioner::directory_move::DirectoryMove;
+use crate::partitioner::match_group::{MatchGroup, SingleFileMatchGroup};
+use crate::partitioner::match_pair::MatchPair;
+use crate::partitioner::MatchPartition;
+use crate::root_alignment::RootAlignment;
+
+/// The report's schema version: incremented whenever a field is changed or removed
+/// (fields may be added without a version change).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Information about one of the logs in a `PartitionReport`
+pub(crate) struct ReportInput<'a> {
+    /// The log's path, as specified
+    pub path: &'a str,
+    /// The log's hash columns, as listed in its header
+    pub hash_columns: &'a [HashAlgorithm],
+    /// Printable warning lines about the log, if any were emitted
+    pub warning_lines: Option<&'a [String]>,
+}
+
+/// The contents of a partition report
+pub(crate) struct PartitionReport<'a> {
+    pub input1: ReportInput<'a>,
+    pub input2: ReportInput<'a>,
+    /// The hash algorithms the logs were compared by (besides size)
+    pub hash_algorithms: &'a [HashAlgorithm],
+    pub info_lines: &'a [String],
+    pub root_alignment: Option<&'a RootAlignment>,
+    pub mp: &'a MatchPartition<'a>,
+    pub directory_moves: &'a [DirectoryMove<'a>],
+}
+
+/// Gets a path as a JSON string, replacing invalid UTF-8.
+fn path_value(path: &[u8]) -> Value {
+    String::from_utf8_lossy(path).into()
+}
+
+/// Gets an entry's fields as a JSON object.
+///
+/// Paths that aren't valid UTF-8 also get a "path_hex" field with their exact bytes.
+fn entry_value(entry: &LogEntry) -> Value {
+    let hashes = entry.digests.iter()
+        .map(|x| (x.algorithm().name().to_string(), x.to_string().into()))
+        .collect::<serde_json::Map<String, Value>>();
+
+    let mut value = json!({
+        "size": entry.size,
+        "hashes": hashes,
+        "path": path_value(&entry.filename),
+    });
+    if std::str::from_utf8(&entry.filename).is_err() {
+        value["path_hex"] = entry.filename.iter().map(|x| format!("{x:02x}")).collect::<String>().into();
+    }
+    value
+}
+
+fn entries_value<'a>(entries: impl IntoIterator<Item = &'a &'a LogEntry>) -> Value {
+    entries.into_iter().map(|&x| entry_value(x)).collect()
+}
+
+fn pairs_value(pairs: &[MatchPair]) -> Value {
+    pairs.iter().map(|x| json!({"file1": entry_value(x.from_file1), "file2": entry_value(x.from_file2)})).collect()
+}
+
+fn groups_value(groups: &[MatchGroup]) -> Value {
+    groups.iter().map(|x| json!({
+        "file1": entries_value(x.from_file1.inner_ref()),
+        "file2": entries_value(x.from_file2.inner_ref()),
+    })).collect()
+}
+
+fn single_file_groups_value(groups: &[SingleFileMatchGroup]) -> Value {
+    groups.iter().map(|x| entries_value(x.log_entries.inner_ref())).collect()
+}
+
+fn directory_moves_value(directory_moves: &[DirectoryMove]) -> Value {
+    directory_moves.iter().map(|x| json!({
+        "from": path_value(&x.from),
+        "to": path_value(&x.to),
+        "moved": x.pairs.len(),
+        "file1_entries": x.file1_entries,
+        "exceptions_file1": entries_value(&x.exceptions_file1),
+        "exceptions_file2": entries_value(&x.exceptions_file2),
+    })).collect()
+}
+
+fn input_value(input: &ReportInput) -> Value {
+    json!({
+        "path": input.path,
+        "hash_columns": input.hash_columns.iter().map(|x| x.name()).collect::<Vec<_>>(),
+        "warnings": input.warning_lines.unwrap_or_default(),
+    })
+}
+
+fn root_alignment_value(root_alignment: &RootAlignment) -> Value {
+    json!({
+        "prefix1": path_value(&root_alignment.prefix1),
+        "prefix2": path_value(&root_alignment.prefix2),
+        "matching_pairs": root_alignment.matching_pairs,
+        "total_pairs": root_alignment.total_pairs,
+        "headers_agree": root_alignment.headers_agree,
+        "applied": root_alignment.applied.map(|(file1, file2)| json!({"file1": file1, "file2": file2})),
+    })
+}
+
+impl PartitionReport<'_> {
+
+    /// Gets the report as a JSON document.
+    ///
+    /// Categories are named as the `part` output files' suffixes, and have the same contents.
+    pub fn to_json(&self) -> Value {
+        let mp = self.mp;
+
+        //(name, value, count): the category's name is also its count's name
+        let categories = [
+            ("full_match_pairs", pairs_value(&mp.full_match_pairs), mp.full_match_pairs.len()),
+            ("full_match_groups_file1_only", single_file_groups_value(&mp.full_match_groups_file1), mp.full_match_groups_file1.len()),
+            ("full_match_groups_file2_only", single_file_groups_value(&mp.full_match_groups_file2), mp.full_match_groups_file2.len()),
+            ("full_match_groups_file1_and_file2", groups_value(&mp.full_match_groups), mp.full_match_groups.len()),
+            ("name_match_pairs", pairs_value(&mp.name_match_pairs), mp.name_match_pairs.len()),
+            ("name_match_groups_file1_only", single_file_groups_value(&mp.name_match_groups_file1), mp.name_match_groups_file1.len()),
+            ("name_match_groups_file2_only", single_file_groups_value(&mp.name_match_groups_file2), mp.name_match_groups_file2.len()),
+            ("name_match_groups_file1_and_file2", groups_value(&mp.name_match_groups), mp.name_match_groups.len()),
+            ("hashes_match_pairs", pairs_value(&mp.hashes_match_pairs), mp.hashes_match_pairs.len()),
+            ("directory_moves", directory_moves_value(self.directory_moves), self.directory_moves.len()),
+            ("hashes_match_groups_file1_only", single_file_groups_value(&mp.hashes_match_groups_file1), mp.hashes_match_groups_file1.len()),
+            ("hashes_match_groups_file2_only", single_file_groups_value(&mp.hashes_match_groups_file2), mp.hashes_match_groups_file2.len()),
+            ("hashes_match_groups_file1_and_file2", groups_value(&mp.hashes_match_groups), mp.hashes_match_groups.len()),
+            ("no_match_entries_file1", entries_value(&mp.no_match_file1), mp.no_match_file1.len()),
+            ("no_match_entries_file2", entries_value(&mp.no_match_file2), mp.no_match_file2.len()),
+        ];
+
+        let mut counts = serde_json::Map::new();
+        let mut values = serde_json::Map::new();
+        for (name, value, count) in categories {
+            counts.insert(name.to_string(), count.into());
+            values.insert(name.to_string(), value);
+        }
+
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "inputs": [input_value(&self.input1), input_value(&self.input2)],
+            "hashes_compared": self.hash_algorithms.iter().map(|x| x.name()).collect::<Vec<_>>(),
+            "info": self.info_lines,
+            "root_alignment": self.root_alignment.map(root_alignment_value),
+            "counts": counts,
+            "categories": values,
+        })
+    }
+
+    /// Writes the report to a new file (will not overwrite an existing file).
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file at `filename` already exists, or
+    /// if an error occurs while writing to the file.
+    pub fn write_to_file(&self, filename: &str) -> Result<(), WriteToFileError> {
+        let json = serde_json::to_string_pretty(&self.to_json())
+            .expect("JSON values always serialize");
+        common::write_lines_to_file([json], filename)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn entry_value_test() {
+        let entry = LogEntry::from_bytes(
+            b"3,0cc175b9c0f1b6a831c399e269772661,file\xe9.txt",
+            &[HashAlgorithm::Md5]).unwrap();
+
+        assert_eq!(entry_value(&entry), json!({
+            "size": 3,
+            "hashes": {"md5": "0cc175b9c0f1b6a831c399e269772661"},
+            "path": "file\u{fffd}.txt",
+            "path_hex": "66696c65e92e747874",
+        }));
+    }
+
+    #[test]
+    fn to_json_test() {
+        let entry = |size: u64, filename: &str| {
+            LogEntry::from_bytes(format!("{size},{size:032x},{filename}").as_bytes(), &[HashAlgorithm::Md5]).unwrap()
+        };
+        let file1 = [entry(1, "a"), entry(2, "b"), entry(3, "c")];
+        let file2 = [entry(1, "a"), entry(2, "moved_b"), entry(4, "c")];
+        let from_file1 = file1.iter().collect::<Vec<_>>();
+        let from_file2 = file2.iter().collect::<Vec<_>>();
+        let mp = crate::partitioner::match_partition(&from_file1, &from_file2, &[HashAlgorithm::Md5]).unwrap();
+
+        let warning_lines = vec!["warning".to_string()];
+        let report = PartitionReport {
+            input1: ReportInput{path: "log1", hash_columns: &[HashAlgorithm::Md5], warning_lines: None},
+            input2: ReportInput{path: "log2", hash_columns: &[HashAlgorithm::Md5], warning_lines: Some(&warning_lines)},
+            hash_algorithms: &[HashAlgorithm::Md5],
+            info_lines: &[],
+            root_alignment: None,
+            mp: &mp,
+            directory_moves: &[],
+        };
+        let json = report.to_json();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["inputs"][1], json!({"path": "log2", "hash_columns": ["md5"], "warnings": ["warning"]}));
+        assert_eq!(json["counts"]["full_match_pairs"], 1);
+        assert_eq!(json["counts"]["name_match_pairs"], 1);
+        assert_eq!(json["counts"]["hashes_match_pairs"], 1);
+        assert_eq!(json["counts"]["no_match_entries_file1"], 0);
+        assert_eq!(json["categories"]["hashes_match_pairs"][0]["file1"]["path"], "b");
+        assert_eq!(json["categories"]["hashes_match_pairs"][0]["file2"]["path"], "moved_b");
+        assert_eq!(json["categories"]["name_match_pairs"][0]["file2"]["size"], 4);
+        assert_eq!(json["root_alignment"], Value::Null);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+
+use crate::log_entry::LogEntry;
+use crate::partitioner::match_pair::MatchPair;
+use crate::partitioner::MatchPartition;
+use crate::root_alignment::prefix_difference;
+
+/// A directory whose files (all or most of them) moved together between two hashdeep logs.
+///
+/// A directory rename is a directory move within the same parent directory.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct DirectoryMove<'a> {
+    /// The directory's path prefix in file 1 (ends with "/", or is empty for the logs' root)
+    pub from: Vec<u8>,
+    /// The directory's path prefix in file 2 (ends with "/", or is empty for the logs' root)
+    pub to: Vec<u8>,
+    /// The hashes match pairs that moved from `from` to `to`
+    pub pairs: Vec<MatchPair<'a>>,
+    /// The number of file 1 entries in `from`
+    pub file1_entries: usize,
+    /// Entries in `from` (in file 1) that didn't move to `to`
+    pub exceptions_file1: Vec<&'a LogEntry>,
+    /// Entries in `to` (in file 2) that didn't move from `from`
+    pub exceptions_file2: Vec<&'a LogEntry>,
+}
+
+impl DirectoryMove<'_> {
+
+    /// Gets a printable description of the move.
+    pub fn description(&self) -> String {
+        let printable = |prefix: &[u8]| if prefix.is_empty() {
+            "./".to_string()
+        } else {
+            String::from_utf8_lossy(prefix).to_string()
+        };
+
+        format!("directory {} -> {} ({} of {} file 1 entries moved, {} exceptions)",
+            printable(&self.from),
+            printable(&self.to),
+            self.pairs.len(),
+            self.file1_entries,
+            self.exceptions_file1.len() + self.exceptions_file2.len())
+    }
+}
+
+/// Gets the longest directory prefix (ending with "/", or empty) that all `paths` share.
+fn common_directory<'a>(mut paths: impl Iterator<Item = &'a [u8]>) -> &'a [u8] {
+    let Some(first) = paths.next() else {
+        return b"";
+    };
+    let common_len = paths.fold(first.len(), |len, path| {
+        first[..len].iter().zip(path).take_while(|(x, y)| x == y).count()
+    });
+    match first[..common_len].iter().rposition(|&c| c == b'/') {
+        Some(i) => &first[..=i],
+        None => b"",
+    }
+}
+
+/// Gets the entries whose paths start with `prefix` (`sorted_entries` must be sorted by path).
+fn entries_in<'a, 'b>(sorted_entries: &'b [&'a LogEntry], prefix: &[u8]) -> &'b [&'a LogEntry] {
+    let start = sorted_entries.partition_point(|x| x.filename.as_slice() < prefix);
+    let len = sorted_entries[start..].partition_point(|x| x.filename.starts_with(prefix));
+    &sorted_entries[start..start + len]
+}
+
+/// Gets the entries of `sorted_entries` that aren't (the same entries as) any of `sorted_moved`
+/// (both must be sorted by path).
+fn exceptions<'a>(sorted_entries: &[&'a LogEntry], sorted_moved: &[&LogEntry]) -> Vec<&'a LogEntry> {
+    sorted_entries.iter()
+        .filter(|&&x| {
+            let start = sorted_moved.partition_point(|y| y.filename < x.filename);
+            !sorted_moved[start..].iter()
+                .take_while(|y| y.filename == x.filename)
+                .any(|&y| std::ptr::eq(x, y))
+        })
+        .copied()
+        .collect()
+}
+
+/// Infers directory moves and renames from a partition's hashes match pairs.
+///
+/// Hashes match pairs whose paths end with the same file name are grouped by their parent directory mapping
+/// (e.g.: `a/x/f` and `a/x/y/g` moved to `b/x/f` and `b/x/y/g` map the directory `a/x/` to `b/x/`):
+/// a mapping is reported if at least 2 pairs, and more than half of the file 1 entries in its directory, moved
+/// together. Entries in the directory that didn't move together (in either file) are listed as exceptions.
+///
+/// The result is sorted by directory paths.
+pub fn infer_directory_moves<'a>(mp: &MatchPartition<'a>) -> Vec<DirectoryMove<'a>> {
+
+    let mut groups = BTreeMap::<(&[u8], &[u8]), Vec<&MatchPair>>::new();
+    for pair in &mp.hashes_match_pairs {
+        if let Some(prefixes) = prefix_difference(&pair.from_file1.filename, &pair.from_file2.filename) {
+            groups.entry(prefixes).or_default().push(pair);
+        }
+    }
+
+    let (mut entries1, mut entries2) = mp.log_entries();
+    entries1.sort_by(|a, b| a.filename.cmp(&b.filename));
+    entries2.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    let mut directory_moves = groups.into_iter()
+        .filter(|(_, pairs)| pairs.len() >= 2)
+        .filter_map(|((prefix1, prefix2), pairs)| {
+
+            //the moved directory is the deepest one that contains all of the pairs' common path suffixes
+            let directory = common_directory(pairs.iter().map(|x| &x.from_file1.filename[prefix1.len()..]));
+            let from = [prefix1, directory].concat();
+            let to = [prefix2, directory].concat();
+
+            let in_from = entries_in(&entries1, &from);
+            if pairs.len() * 2 <= in_from.len() {
+                return None;
+            }
+
+            let mut moved1 = pairs.iter().map(|x| x.from_file1).collect::<Vec<_>>();
+            let mut moved2 = pairs.iter().map(|x| x.from_file2).collect::<Vec<_>>();
+            moved1.sort_by(|a, b| a.filename.cmp(&b.filename));
+            moved2.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+            Some(DirectoryMove {
+                exceptions_file1: exceptions(in_from, &moved1),
+                exceptions_file2: exceptions(entries_in(&entries2, &to), &moved2),
+                file1_entries: in_from.len(),
+                pairs: pairs.into_iter().cloned().collect(),
+                from,
+                to,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    directory_moves.sort();
+    directory_moves
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::digest::HashAlgorithm;
+    use crate::partitioner::match_partition;
+
+    #[test]
+    fn common_directory_test() {
+        let paths = |x: &[&'static str]| x.iter().map(|x| x.as_bytes()).collect::<Vec<_>>().into_iter();
+        assert_eq!(common_directory(paths(&["b/f", "b/c/g"])), b"b/");
+        assert_eq!(common_directory(paths(&["b/c/f", "b/c/g"])), b"b/c/");
+        assert_eq!(common_directory(paths(&["bc/f", "bd/g"])), b"");
+        assert_eq!(common_directory(paths(&["f", "g"])), b"");
+        assert_eq!(common_directory(paths(&[])), b"");
+    }
+
+    #[test]
+    fn infer_directory_moves_test() {
+        let entry = |size: u64, filename: &str| {
+            LogEntry::from_bytes(format!("{size},{size:032x},{filename}").as_bytes(), &[HashAlgorithm::Md5]).unwrap()
+        };
+        let file1 = vec![
+            entry(1, "x/a/f"), entry(2, "x/a/b/g"), entry(3, "x/a/h"), entry(4, "x/a/unchanged"),
+            entry(5, "x/c/f"), entry(6, "x/c/g"), entry(7, "x/c/h"), entry(8, "x/c/i"), entry(9, "x/c/j"),
+        ];
+        let file2 = vec![
+            entry(1, "y/a/f"), entry(2, "y/a/b/g"), entry(3, "y/a/h"), entry(4, "x/a/unchanged"), entry(10, "y/a/new"),
+            //only 2 of 5 moved
+            entry(5, "z/f"), entry(6, "z/g"), entry(7, "x/c/h"), entry(8, "x/c/i"), entry(9, "x/c/j"),
+        ];
+        let from_file1 = file1.iter().collect::<Vec<_>>();
+        let from_file2 = file2.iter().collect::<Vec<_>>();
+        let mp = match_partition(&from_file1, &from_file2, &[HashAlgorithm::Md5]).unwrap();
+
+        let moves = infer_directory_moves(&mp);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].from, b"x/a/");
+        assert_eq!(moves[0].to, b"y/a/");
+        assert_eq!(moves[0].pairs.len(), 3);
+        assert_eq!(moves[0].file1_entries, 4);
+        assert_eq!(moves[0].exceptions_file1, vec![&file1[3]]);
+        assert_eq!(moves[0].exceptions_file2, vec![&file2[4]]);
+        assert_eq!(moves[0].description(), "directory x/a/ -> y/a/ (3 of 4 file 1 entries moved, 2 exceptions)");
+    }
+
+    #[test]
+    fn infer_directory_moves_threshold_test() {
+        let entry = |size: u64, filename: &str| {
+            LogEntry::from_bytes(format!("{size},{size:032x},{filename}").as_bytes(), &[HashAlgorithm::Md5]).unwrap()
+        };
+        let file1 = [
+            entry(1, "x/a/f"), entry(2, "x/a/g"), entry(3, "x/a/h"), entry(4, "x/a/i"),
+            entry(5, "s/f"),
+        ];
+        let file2 = [
+            //exactly half of x/a/ moved
+            entry(1, "y/a/f"), entry(2, "y/a/g"), entry(3, "x/a/h"), entry(4, "x/a/i"),
+            //all of s/ moved, but that's only 1 pair
+            entry(5, "t/f"),
+        ];
+        let from_file1 = file1.iter().collect::<Vec<_>>();
+        let from_file2 = file2.iter().collect::<Vec<_>>();
+        let mp = match_partition(&from_file1, &from_file2, &[HashAlgorithm::Md5]).unwrap();
+        assert_eq!(mp.hashes_match_pairs.len(), 3);
+
+        assert_eq!(infer_directory_moves(&mp), vec![]);
+
+        //with one more pair, more than half of x/a/ moved
+        let file2 = [
+            entry(1, "y/a/f"), entry(2, "y/a/g"), entry(3, "y/a/h"), entry(4, "x/a/i"), entry(6, "y/a/j"),
+            entry(5, "t/f"),
+        ];
+        let from_file2 = file2.iter().collect::<Vec<_>>();
+        let mp = match_partition(&from_file1, &from_file2, &[HashAlgorithm::Md5]).unwrap();
+
+        let moves = infer_directory_moves(&mp);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].description(), "directory x/a/ -> y/a/ (3 of 4 file 1 entries moved, 2 exceptions)");
+        assert_eq!(moves[0].exceptions_file1, vec![&file1[3]]);
+        assert_eq!(moves[0].exceptions_file2, vec![&file2[4]]);
+    }
+}
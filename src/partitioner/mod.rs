@@ -1,11 +1,13 @@
 pub mod match_pair;
 pub mod match_group;
+pub mod directory_move;
 
 use std::collections::BTreeMap;
 use thiserror::Error;
 
 use self::match_pair::MatchPair;
 use self::match_group::{MatchGroup,SingleFileMatchGroup};
+use crate::digest::HashAlgorithm;
 use crate::log_entry::LogEntry;
 use crate::some_vec::SomeVec;
 
@@ -35,6 +37,31 @@ pub struct MatchPartition<'a> {
 
 impl<'a> MatchPartition<'a> {
 
+    /// Gets all entry references from file 1, and all from file 2 (in no particular order).
+    pub fn log_entries(&self) -> (Vec<&'a LogEntry>, Vec<&'a LogEntry>) {
+        let mut from_file1 = vec![];
+        let mut from_file2 = vec![];
+
+        for pairs in [&self.full_match_pairs, &self.name_match_pairs, &self.hashes_match_pairs] {
+            from_file1.extend(pairs.iter().map(|x| x.from_file1));
+            from_file2.extend(pairs.iter().map(|x| x.from_file2));
+        }
+        for groups in [&self.full_match_groups, &self.name_match_groups, &self.hashes_match_groups] {
+            from_file1.extend(groups.iter().flat_map(|x| x.from_file1.inner_ref().iter().copied()));
+            from_file2.extend(groups.iter().flat_map(|x| x.from_file2.inner_ref().iter().copied()));
+        }
+        for groups in [&self.full_match_groups_file1, &self.name_match_groups_file1, &self.hashes_match_groups_file1] {
+            from_file1.extend(groups.iter().flat_map(|x| x.log_entries.inner_ref().iter().copied()));
+        }
+        for groups in [&self.full_match_groups_file2, &self.name_match_groups_file2, &self.hashes_match_groups_file2] {
+            from_file2.extend(groups.iter().flat_map(|x| x.log_entries.inner_ref().iter().copied()));
+        }
+        from_file1.extend(self.no_match_file1.iter().copied());
+        from_file2.extend(self.no_match_file2.iter().copied());
+
+        (from_file1, from_file2)
+    }
+
     fn total_log_entries(&self) -> Option<usize> {
 
         fn pairs_sum(pairs: &[MatchPair]) -> Option<usize> {
@@ -87,39 +114,60 @@ pub enum MatchPartitionError {
 /// comparison of the two source hashdeep log files in terms of inferred
 /// intervening file changes.
 ///
+/// File content is compared by size and by the digests of `hash_algorithms`
+/// (which should be available in the entries of both files).
+///
+/// # Errors
+///
+/// An integrity check is run on the results of the partitioning operation.
+/// An error will be issued if this check fails (this is extremely unlikely).
+pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEntry], hash_algorithms: &[HashAlgorithm])
+    -> Result<MatchPartition<'b>, MatchPartitionError> {
+
+    let as_themselves = |entries: &[&'b LogEntry]| entries.iter().map(|&x| (x, x)).collect::<Vec<_>>();
+    match_partition_as(&as_themselves(from_file1), &as_themselves(from_file2), hash_algorithms)
+}
+
+/// Partitions entries as `match_partition` does, but matches each entry as another one:
+/// each element of `from_file1` and `from_file2` is (the entry to match as, the entry to partition)
+/// (e.g.: a copy of an entry with a rewritten path, and the entry itself).
+///
+/// Entries are sorted by the paths of the partitioned entries.
+///
 /// # Errors
 ///
 /// An integrity check is run on the results of the partitioning operation.
 /// An error will be issued if this check fails (this is extremely unlikely).
-pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEntry]) -> Result<MatchPartition<'b>, MatchPartitionError> {
+pub fn match_partition_as<'b>(from_file1: &[(&LogEntry, &'b LogEntry)], from_file2: &[(&LogEntry, &'b LogEntry)], hash_algorithms: &[HashAlgorithm])
+    -> Result<MatchPartition<'b>, MatchPartitionError> {
 
-    struct SortedMatches<'a> {
+    struct SortedMatches<'m, 'a> {
         match_pairs: Vec<MatchPair<'a>>,
         match_groups: Vec<MatchGroup<'a>>,
         match_groups_file1: Vec<SingleFileMatchGroup<'a>>,
         match_groups_file2: Vec<SingleFileMatchGroup<'a>>,
-        no_match_file1: Vec<&'a LogEntry>,
-        no_match_file2: Vec<&'a LogEntry>,
+        no_match_file1: Vec<(&'m LogEntry, &'a LogEntry)>,
+        no_match_file2: Vec<(&'m LogEntry, &'a LogEntry)>,
     }
 
-    fn sort_matches<'c, F>(from_file1: &[&'c LogEntry], from_file2: &[&'c LogEntry], f: F) -> SortedMatches<'c>
-        where F: Fn(&LogEntry) -> String
+    fn sort_matches<'m, 'c, F, K>(from_file1: &[(&'m LogEntry, &'c LogEntry)], from_file2: &[(&'m LogEntry, &'c LogEntry)], f: F) -> SortedMatches<'m, 'c>
+        where F: Fn(&LogEntry) -> K, K: Ord
     {
-        enum LogEntryFrom<'a> {
-            File1(&'a LogEntry),
-            File2(&'a LogEntry),
+        enum LogEntryFrom<'m, 'a> {
+            File1((&'m LogEntry, &'a LogEntry)),
+            File2((&'m LogEntry, &'a LogEntry)),
         }
 
-        let mut matches = BTreeMap::<String, SomeVec<LogEntryFrom>>::new();
+        let mut matches = BTreeMap::<K, SomeVec<LogEntryFrom>>::new();
 
         for &i in from_file1 {
-            matches.entry(f(i))
+            matches.entry(f(i.0))
                 .and_modify(|x| x.push(LogEntryFrom::File1(i)))
                 .or_insert_with(|| SomeVec::<LogEntryFrom>::from_first_value(LogEntryFrom::File1(i)));
         }
 
         for &i in from_file2 {
-            matches.entry(f(i))
+            matches.entry(f(i.0))
                 .and_modify(|x| x.push(LogEntryFrom::File2(i)))
                 .or_insert_with(|| SomeVec::<LogEntryFrom>::from_first_value(LogEntryFrom::File2(i)));
         }
@@ -129,21 +177,21 @@ pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEnt
         let mut match_groups = Vec::<MatchGroup>::new();
         let mut match_groups_file1 = Vec::<SingleFileMatchGroup>::new();
         let mut match_groups_file2 = Vec::<SingleFileMatchGroup>::new();
-        let mut no_match_file1 = Vec::<&LogEntry>::new();
-        let mut no_match_file2 = Vec::<&LogEntry>::new();
+        let mut no_match_file1 = Vec::<(&LogEntry, &LogEntry)>::new();
+        let mut no_match_file2 = Vec::<(&LogEntry, &LogEntry)>::new();
 
         for (_, v) in matches {
             match v.len() {
                 0 => unreachable!(), //SomeVec.len() is always positive
                 1 => match v.at(0) {
-                    LogEntryFrom::File1(x) => no_match_file1.push(x),
-                    LogEntryFrom::File2(x) => no_match_file2.push(x),
+                    LogEntryFrom::File1(x) => no_match_file1.push(*x),
+                    LogEntryFrom::File2(x) => no_match_file2.push(*x),
                 },
                 2 => match (&v.at(0), &v.at(1)) {
-                    (LogEntryFrom::File1(x),LogEntryFrom::File1(y)) => match_groups_file1.push(SingleFileMatchGroup{log_entries: SomeVec::from_values(*x,*y)}),
-                    (LogEntryFrom::File1(x),LogEntryFrom::File2(y)) => match_pairs.push(MatchPair{from_file1: x, from_file2: y}),
-                    (LogEntryFrom::File2(x),LogEntryFrom::File1(y)) => match_pairs.push(MatchPair{from_file1: y, from_file2: x}),
-                    (LogEntryFrom::File2(x),LogEntryFrom::File2(y)) => match_groups_file2.push(SingleFileMatchGroup{log_entries: SomeVec::from_values(*x,*y)}),
+                    (LogEntryFrom::File1(x),LogEntryFrom::File1(y)) => match_groups_file1.push(SingleFileMatchGroup{log_entries: SomeVec::from_values(x.1,y.1)}),
+                    (LogEntryFrom::File1(x),LogEntryFrom::File2(y)) => match_pairs.push(MatchPair{from_file1: x.1, from_file2: y.1}),
+                    (LogEntryFrom::File2(x),LogEntryFrom::File1(y)) => match_pairs.push(MatchPair{from_file1: y.1, from_file2: x.1}),
+                    (LogEntryFrom::File2(x),LogEntryFrom::File2(y)) => match_groups_file2.push(SingleFileMatchGroup{log_entries: SomeVec::from_values(x.1,y.1)}),
                 },
                 _ => {
                     let mut from_file1 = Vec::<&LogEntry>::new();
@@ -151,8 +199,8 @@ pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEnt
 
                     for i in v.inner_ref() {
                         match i {
-                            LogEntryFrom::File1(x) => from_file1.push(x),
-                            LogEntryFrom::File2(x) => from_file2.push(x),
+                            LogEntryFrom::File1(x) => from_file1.push(x.1),
+                            LogEntryFrom::File2(x) => from_file2.push(x.1),
                         }
                     }
 
@@ -169,11 +217,11 @@ pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEnt
         SortedMatches { match_pairs, match_groups, match_groups_file1, match_groups_file2, no_match_file1, no_match_file2 }
     }
 
-    let full_matches = sort_matches(from_file1, from_file2, |x| x.to_string());
+    let full_matches = sort_matches(from_file1, from_file2, |x| (x.content_key(hash_algorithms), x.filename.clone()));
 
     let name_matches = sort_matches(&full_matches.no_match_file1, &full_matches.no_match_file2, |x| x.filename.clone());
 
-    let hashes_matches = sort_matches(&name_matches.no_match_file1, &name_matches.no_match_file2, |x| x.hashes.clone());
+    let hashes_matches = sort_matches(&name_matches.no_match_file1, &name_matches.no_match_file2, |x| x.content_key(hash_algorithms));
 
     let mut mp = MatchPartition {
 
@@ -192,8 +240,8 @@ pub fn match_partition<'b>(from_file1: &[&'b LogEntry], from_file2: &[&'b LogEnt
         hashes_match_groups_file1: hashes_matches.match_groups_file1,
         hashes_match_groups_file2: hashes_matches.match_groups_file2,
 
-        no_match_file1: hashes_matches.no_match_file1,
-        no_match_file2: hashes_matches.no_match_file2,
+        no_match_file1: hashes_matches.no_match_file1.into_iter().map(|(_, x)| x).collect(),
+        no_match_file2: hashes_matches.no_match_file2.into_iter().map(|(_, x)| x).collect(),
     };
 
     fn sort_match_pairs_by_filename(x: &mut [MatchPair]) {
@@ -270,7 +318,7 @@ mod test {
         let from_file1 = file1.entries.iter().collect::<Vec<&LogEntry>>();
         let from_file2 = file2.entries.iter().collect::<Vec<&LogEntry>>();
 
-        let mp = match_partition(&from_file1, &from_file2).unwrap();
+        let mp = match_partition(&from_file1, &from_file2, &HashAlgorithm::DEFAULT_COLUMNS).unwrap();
 
         assert_eq!(2, mp.full_match_pairs.len());
         assert_eq!(1, mp.full_match_groups_file1.len());
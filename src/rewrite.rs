@@ -0,0 +1,349 @@
+//! Rewriting the file paths of a hashdeep log with ordered rules (e.g.: after a volume reorganisation)
+//!
+//! Rules are given on the command line as words, or in a rules file as 1 rule per line
+//! (with tab-separated fields; blank lines and lines starting with '#' are skipped):
+//!
+//! >`add` PREFIX: adds a prefix to every path
+//! >
+//! >`replace` FROM TO: replaces a prefix, matched by whole path components (as by `root`)
+//! >
+//! >`regex` PATTERN REPLACEMENT: replaces the first match of a regular expression
+//! >(the replacement may refer to capture groups, e.g.: `$1` or `${name}`)
+
+use std::io::ErrorKind;
+
+use regex::bytes::Regex;
+use thiserror::Error;
+
+use crate::log_entry::LogEntry;
+use crate::log_ops;
+use crate::root::{self, PathPrefix, PathPrefixMatch};
+
+#[derive(Error, Debug)]
+pub enum RewriteRuleError {
+    #[error("\"{0}\" cannot be opened for reading (not found)")]
+    FileNotFound(String),
+
+    #[error("\"{0}\" cannot be opened for reading ({})", .1)]
+    OtherIoError(String, #[source] std::io::Error),
+
+    #[error("invalid rewrite rule ({location}): {message}")]
+    InvalidRule{location: String, message: String},
+
+    #[error("no rewrite rules were given")]
+    NoRules,
+}
+
+/// A file path rewriting rule
+#[derive(Clone, Debug)]
+pub enum RewriteRule {
+    /// Adds a prefix to every path (leading "./" and "/" are removed from the path first)
+    AddPrefix(Vec<u8>),
+    /// Replaces a prefix, matched by whole path components
+    ReplacePrefix{from: Vec<u8>, to: Vec<u8>},
+    /// Replaces the first match of a regular expression (with `$1`-style references to capture groups)
+    Regex{pattern: Regex, replacement: Vec<u8>},
+}
+
+/// Joins a prefix and a relative path with 1 separator.
+fn join(prefix: &[u8], path: &[u8]) -> Vec<u8> {
+    if prefix.is_empty() || prefix.ends_with(b"/") {
+        [prefix, path].concat()
+    }
+    else {
+        [prefix, b"/", path].concat()
+    }
+}
+
+impl RewriteRule {
+
+    /// Parses a rule from its keyword and arguments.
+    fn parse<'a>(keyword: &[u8], args: &mut impl Iterator<Item = &'a [u8]>) -> Result<Self, String> {
+
+        let mut arg = |name: &str| args.next()
+            .map(|x| x.to_vec())
+            .ok_or_else(|| format!("\"{}\" is missing its {name}", String::from_utf8_lossy(keyword)));
+
+        match keyword {
+            b"add" => Ok(RewriteRule::AddPrefix(arg("prefix")?)),
+            b"replace" => Ok(RewriteRule::ReplacePrefix{from: arg("prefix to replace")?, to: arg("new prefix")?}),
+            b"regex" => {
+                let pattern = arg("pattern")?;
+                let pattern = std::str::from_utf8(&pattern)
+                    .map_err(|_| "regex pattern is not valid UTF-8".to_string())?;
+                let pattern = Regex::new(pattern)
+                    .map_err(|e| format!("invalid regex pattern ({})", e.to_string().lines().last().unwrap_or_default().trim()))?;
+                Ok(RewriteRule::Regex{pattern, replacement: arg("replacement")?})
+            },
+            x => Err(format!("unknown rule \"{}\" (expected add, replace or regex)", String::from_utf8_lossy(x))),
+        }
+    }
+
+    /// Gets the rewritten path, or None if the rule doesn't match the path.
+    pub fn apply(&self, path: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            RewriteRule::AddPrefix(prefix) => Some(join(prefix, root::skip_separators(path))),
+            RewriteRule::ReplacePrefix{from, to} => match root::strip_path_prefix(path, &PathPrefix::new(from)) {
+                PathPrefixMatch::Match(rest) => Some(join(to, rest)),
+                PathPrefixMatch::NoMatch | PathPrefixMatch::SplitComponent => None,
+            },
+            RewriteRule::Regex{pattern, replacement} => {
+                pattern.is_match(path).then(|| pattern.replacen(path, 1, replacement.as_slice()).into_owned())
+            },
+        }
+    }
+
+    /// Gets a printable description of the rule.
+    pub fn description(&self) -> String {
+        let s = |x: &[u8]| String::from_utf8_lossy(x).into_owned();
+        match self {
+            RewriteRule::AddPrefix(prefix) => format!("add \"{}\"", s(prefix)),
+            RewriteRule::ReplacePrefix{from, to} => format!("replace \"{}\" with \"{}\"", s(from), s(to)),
+            RewriteRule::Regex{pattern, replacement} => format!("regex \"{pattern}\" to \"{}\"", s(replacement)),
+        }
+    }
+}
+
+/// Parses rules from command line words (e.g.: `replace /mnt/old/ /srv/new/ add backup`).
+///
+/// # Errors
+///
+/// A `RewriteRuleError::InvalidRule` error will be returned if the words aren't a sequence of valid rules.
+pub fn parse_rules(words: &[String]) -> Result<Vec<RewriteRule>, RewriteRuleError> {
+
+    let mut words = words.iter().map(|x| x.as_bytes());
+    let mut rules = vec![];
+    while let Some(keyword) = words.next() {
+        let rule = RewriteRule::parse(keyword, &mut words)
+            .map_err(|message| RewriteRuleError::InvalidRule{location: format!("command line rule {}", rules.len() + 1), message})?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Reads rules from a rules file: 1 rule per line, with tab-separated fields
+/// (blank lines and lines starting with '#' are skipped).
+///
+/// # Errors
+///
+/// An error will be returned if the file can't be read, or if any line isn't a valid rule.
+pub fn read_rules_file(path: &str) -> Result<Vec<RewriteRule>, RewriteRuleError> {
+
+    let contents = std::fs::read(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => RewriteRuleError::FileNotFound(path.to_string()),
+        _ => RewriteRuleError::OtherIoError(path.to_string(), e),
+    })?;
+
+    let mut rules = vec![];
+    for (i, line) in contents.split(|&c| c == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.iter().all(u8::is_ascii_whitespace) || line.starts_with(b"#") {
+            continue;
+        }
+
+        let invalid_rule = |message| RewriteRuleError::InvalidRule{location: format!("{path} line {}", i + 1), message};
+
+        let mut fields = line.split(|&c| c == b'\t');
+        let keyword = fields.next().unwrap_or_default();
+        rules.push(RewriteRule::parse(keyword, &mut fields).map_err(invalid_rule)?);
+        if fields.next().is_some() {
+            return Err(invalid_rule("too many fields (fields are separated by tabs)".to_string()));
+        }
+    }
+    Ok(rules)
+}
+
+/// Settings for `rewrite_paths`
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct RewriteOptions {
+    /// If true, entries that match no rule are omitted (by default, they are kept unchanged)
+    pub drop_unmatched: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct RewriteSuccess
+{
+    /// Printable info lines
+    pub info_lines: Vec<String>,
+    /// Printable warning lines
+    pub warning_lines: Vec<String>,
+    /// Printable warning lines about the hashdeep log file, if any were emitted
+    pub file_warning_lines: Option<Vec<String>>,
+}
+
+fn info_lines(rules: &[RewriteRule], rule_matches: &[usize], entries_unmatched: usize, options: RewriteOptions) -> Vec<String> {
+    let mut v = vec![];
+
+    let total_entries = rule_matches.iter().try_fold(entries_unmatched, |acc, &x| acc.checked_add(x))
+        .expect("entry stats should not cause arithmetic overflow");
+    v.push(format!("Input file contains {total_entries} entries:"));
+
+    for (i, (rule, matches)) in rules.iter().zip(rule_matches).enumerate() {
+        v.push(format!("  {matches} entries rewritten by rule {} ({})", i + 1, rule.description()));
+    }
+    if entries_unmatched > 0 {
+        let action = if options.drop_unmatched {"omitted"} else {"kept unchanged"};
+        v.push(format!("  {entries_unmatched} entries matched no rule and were {action}"));
+    }
+    v
+}
+
+fn warning_lines(rule_matches: &[usize], entries_unmatched: usize, options: RewriteOptions) -> Vec<String> {
+    let mut v = vec![];
+
+    let entries_matched: usize = rule_matches.iter().sum();
+    if entries_matched == 0 && entries_unmatched == 0 {
+        v.push("Warning: No entries were loaded from the input file".to_string());
+        return v;
+    }
+
+    if entries_matched == 0 && options.drop_unmatched {
+        v.push("Warning: No entries matched any rule (All entries were omitted)".to_string());
+    }
+    for (i, _) in rule_matches.iter().enumerate().filter(|(_, &x)| x == 0) {
+        v.push(format!("Warning: Rule {} matched no entries", i + 1));
+    }
+    v
+}
+
+/// Reads a hashdeep log file and writes its entries to a new file, with their file paths rewritten by `rules`.
+///
+/// Each entry is rewritten by the first rule (in order) that matches its path. Entries that match no rule
+/// are kept unchanged, or omitted with `options.drop_unmatched`.
+///
+/// # Errors
+///
+/// A `RewriteRuleError::NoRules` error will be returned if `rules` is empty.
+/// Any error emitted while reading or writing the files will be returned.
+pub fn rewrite_paths(filename: &str, out_filename: &str, rules: &[RewriteRule], options: RewriteOptions)
+    -> Result<RewriteSuccess, Box<dyn std::error::Error>> {
+
+    if rules.is_empty() {
+        return Err(RewriteRuleError::NoRules.into());
+    }
+
+    let mut rule_matches = vec![0usize; rules.len()];
+    let mut entries_unmatched: usize = 0;
+
+    let f = |log_entry: LogEntry| -> Result<Option<LogEntry>, Box<dyn std::error::Error>> {
+
+        let rewritten = rules.iter().enumerate()
+            .find_map(|(i, rule)| rule.apply(&log_entry.filename).map(|x| (i, x)));
+
+        match rewritten {
+            Some((i, new_path)) => {
+                rule_matches[i] += 1;
+                Ok(Some(LogEntry{filename: new_path, ..log_entry}))
+            },
+            None => {
+                entries_unmatched += 1;
+                Ok((!options.drop_unmatched).then_some(log_entry))
+            },
+        }
+    };
+
+    let file_warning_lines = log_ops::process_log_entries(filename, out_filename, f)?;
+
+    let info_lines = info_lines(rules, &rule_matches, entries_unmatched, options);
+    let warning_lines = warning_lines(&rule_matches, entries_unmatched, options);
+
+    Ok(RewriteSuccess{info_lines, warning_lines, file_warning_lines})
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(s: &str) -> Vec<String> {
+        s.split(' ').map(|x| x.to_string()).collect()
+    }
+
+    fn apply(rules: &[RewriteRule], path: &str) -> Option<String> {
+        rules.iter().find_map(|x| x.apply(path.as_bytes())).map(|x| String::from_utf8(x).unwrap())
+    }
+
+    #[test]
+    fn rewrite_rule_apply_test() {
+        let rules = parse_rules(&words("replace /mnt/old/ /srv/new/ replace old new add archive")).unwrap();
+        assert_eq!(apply(&rules, "/mnt/old/dir/file"), Some("/srv/new/dir/file".to_string()));
+        assert_eq!(apply(&rules, "/mnt//old/file"), Some("/srv/new/file".to_string()));
+        assert_eq!(apply(&rules, "./old/file"), Some("new/file".to_string()));
+        assert_eq!(apply(&rules, "./older/file"), Some("archive/older/file".to_string()));
+        assert_eq!(apply(&rules, "/mnt/other/file"), Some("archive/mnt/other/file".to_string()));
+
+        let rules = parse_rules(&words(r"regex ^photos/(\d{4})-(\d\d)/ photos/$1/$2/")).unwrap();
+        assert_eq!(apply(&rules, "photos/2021-07/a.jpg"), Some("photos/2021/07/a.jpg".to_string()));
+        assert_eq!(apply(&rules, "photos/misc/a.jpg"), None);
+
+        let rules = parse_rules(&words("replace a b")).unwrap();
+        assert_eq!(apply(&rules, "ab/c"), None);
+        assert_eq!(apply(&rules, "a"), None);
+    }
+
+    #[test]
+    fn parse_rules_test() {
+        assert_eq!(parse_rules(&[]).unwrap().len(), 0);
+        assert_eq!(parse_rules(&words("add a regex . b replace c d")).unwrap().len(), 3);
+
+        let message = |s: &str| parse_rules(&words(s)).unwrap_err().to_string();
+        assert_eq!(message("add a remove b"),
+                   "invalid rewrite rule (command line rule 2): unknown rule \"remove\" (expected add, replace or regex)");
+        assert_eq!(message("replace a"),
+                   "invalid rewrite rule (command line rule 1): \"replace\" is missing its new prefix");
+        assert_eq!(message("regex ( a"),
+                   "invalid rewrite rule (command line rule 1): invalid regex pattern (error: unclosed group)");
+    }
+
+    #[test]
+    fn read_rules_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let rules_file = temp_dir.path().join("rules.txt");
+        let rules_file_str = rules_file.to_str().unwrap();
+
+        std::fs::write(&rules_file, "# comment\n\nreplace\t/mnt/old dir/\t/srv/new\r\nadd\tx y\n").unwrap();
+        let rules = read_rules_file(rules_file_str).unwrap();
+        assert_eq!(apply(&rules, "/mnt/old dir/a"), Some("/srv/new/a".to_string()));
+        assert_eq!(apply(&rules, "a"), Some("x y/a".to_string()));
+
+        std::fs::write(&rules_file, "add\ta\n\nadd\tb\tc\n").unwrap();
+        assert_eq!(read_rules_file(rules_file_str).unwrap_err().to_string(),
+                   format!("invalid rewrite rule ({rules_file_str} line 3): too many fields (fields are separated by tabs)"));
+
+        assert!(matches!(read_rules_file("does_not_exist").unwrap_err(), RewriteRuleError::FileNotFound(_)));
+    }
+
+    #[test]
+    fn rewrite_paths_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let rules = parse_rules(&words("replace hashdeepComp /srv/hashdeepComp regex ^renameGroup(.) group$1")).unwrap();
+
+        let rewritten = |options: RewriteOptions| {
+            let output = temp_dir.path().join(format!("{options:?}"));
+            let success = rewrite_paths("tests/test1.txt", output.to_str().unwrap(), &rules, options).unwrap();
+            let filenames = std::fs::read_to_string(output).unwrap().lines().skip(5)
+                .map(|x| x.rsplit(',').next().unwrap().to_string())
+                .collect::<Vec<_>>();
+            (success, filenames)
+        };
+
+        let (success, filenames) = rewritten(RewriteOptions::default());
+        assert_eq!(filenames, [
+            "/srv/hashdeepComp/345.txt", "/srv/hashdeepComp/234.txt", "/srv/hashdeepComp/456.txt", "/srv/hashdeepComp/123.txt",
+            "groupA.txt", "groupB.txt", "onlyInTest1.txt",
+        ]);
+        assert_eq!(success.info_lines, [
+            "Input file contains 7 entries:",
+            "  4 entries rewritten by rule 1 (replace \"hashdeepComp\" with \"/srv/hashdeepComp\")",
+            "  2 entries rewritten by rule 2 (regex \"^renameGroup(.)\" to \"group$1\")",
+            "  1 entries matched no rule and were kept unchanged",
+        ]);
+        assert!(success.warning_lines.is_empty());
+
+        let (success, filenames) = rewritten(RewriteOptions{drop_unmatched: true});
+        assert_eq!(filenames.len(), 6);
+        assert_eq!(success.info_lines[3], "  1 entries matched no rule and were omitted");
+
+        let err = rewrite_paths("tests/test1.txt", "unused", &[], RewriteOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "no rewrite rules were given");
+    }
+}
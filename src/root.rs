@@ -1,7 +1,21 @@
-use crate::common::LogFile;
 use crate::log_entry::LogEntry;
 use crate::log_ops;
 
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChangeRootError {
+    #[error("prefix \"{prefix}\" ends inside a path component of \"{path}\" (to remove it as text, use --raw)")]
+    SplitPathComponent{prefix: String, path: String},
+}
+
+/// Settings for `change_root`
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct ChangeRootOptions {
+    /// If true, the prefix is removed as text (without any rules related to paths)
+    pub raw: bool,
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
 pub struct ChangeRootSuccess
 {
@@ -31,7 +45,7 @@ fn info_lines(entries_matched: usize, entries_omitted: usize) -> Vec<String> {
     v
 }
 
-fn warning_lines(entries_matched: usize, entries_omitted: usize, root_prefix: &str) -> Vec<String> {
+fn warning_lines(entries_matched: usize, entries_omitted: usize, prefix_is_empty: bool) -> Vec<String> {
     let mut v = vec![];
 
     if entries_matched == 0 && entries_omitted == 0 {
@@ -41,52 +55,164 @@ fn warning_lines(entries_matched: usize, entries_omitted: usize, root_prefix: &s
         v.push("Warning: No entries matched the prefix (All entries were omitted)".to_string());
     }
 
-    if root_prefix.is_empty() {
+    if prefix_is_empty {
         v.push("Warning: Prefix is empty (operation will have no effect)".to_string());
     }
     v
 }
 
+/// A path prefix, as the path components it must match
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct PathPrefix<'a> {
+    /// If true, only absolute paths (that start with "/") match
+    absolute: bool,
+    /// The prefix's components, without empty ("//") and "." components
+    components: Vec<&'a [u8]>,
+    /// If true, the prefix ends with a separator (e.g.: "dir/"), so its last component must be
+    /// a whole path component
+    ends_with_separator: bool,
+}
+
+impl<'a> PathPrefix<'a> {
+    pub(crate) fn new(prefix: &'a [u8]) -> Self {
+        let parts = prefix.split(|&c| c == b'/');
+        PathPrefix {
+            absolute: prefix.starts_with(b"/"),
+            ends_with_separator: parts.clone().next_back().is_some_and(|x| x.is_empty() || x == b"."),
+            components: parts.filter(|x| !x.is_empty() && *x != b".").collect(),
+        }
+    }
+
+    /// True if the prefix matches every path, and removes nothing
+    fn is_empty(&self) -> bool {
+        !self.absolute && self.components.is_empty()
+    }
+}
+
+/// The result of matching a path against a `PathPrefix`
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub(crate) enum PathPrefixMatch<'a> {
+    /// The path starts with the prefix: this is the rest of the path
+    Match(&'a [u8]),
+    NoMatch,
+    /// The prefix's last component is the start of (but not all of) a path component
+    /// (for a prefix that doesn't end with a separator: otherwise, this is `NoMatch`)
+    SplitComponent,
+}
+
+/// Removes any leading separators and "." components from a path.
+pub(crate) fn skip_separators(mut path: &[u8]) -> &[u8] {
+    loop {
+        if let Some(rest) = path.strip_prefix(b"/").or_else(|| path.strip_prefix(b"./")) {
+            path = rest;
+        }
+        else if path == b"." {
+            return b"";
+        }
+        else {
+            return path;
+        }
+    }
+}
+
+/// Matches a path against a prefix by whole path components.
+///
+/// Separators and "." components are skipped (so "./dir//file" matches "dir/"), but the rest of the path
+/// is returned as-is. A path that is only the prefix (with nothing after it) doesn't match.
+///
+/// A prefix that ends inside a path component (e.g.: "dir" on "dirt/file") is only reported as
+/// `SplitComponent` if the prefix doesn't end with a separator: "dir/" just doesn't match "dirt/file".
+pub(crate) fn strip_path_prefix<'a>(path: &'a [u8], prefix: &PathPrefix) -> PathPrefixMatch<'a> {
+    if prefix.is_empty() {
+        return PathPrefixMatch::Match(path);
+    }
+    if prefix.absolute != path.starts_with(b"/") {
+        return PathPrefixMatch::NoMatch;
+    }
+
+    let mut rest = path;
+    for (i, &prefix_component) in prefix.components.iter().enumerate() {
+        rest = skip_separators(rest);
+        let (component, after) = rest.split_at(rest.iter().position(|&c| c == b'/').unwrap_or(rest.len()));
+
+        if component != prefix_component {
+            let is_last = i + 1 == prefix.components.len();
+            return if is_last && !prefix.ends_with_separator && component.starts_with(prefix_component) {
+                PathPrefixMatch::SplitComponent
+            } else {
+                PathPrefixMatch::NoMatch
+            };
+        }
+        rest = after;
+    }
+
+    match skip_separators(rest) {
+        b"" => PathPrefixMatch::NoMatch,
+        rest => PathPrefixMatch::Match(rest),
+    }
+}
+
 /// Reads a hashdeep log file and writes its entries to a new file, with
 /// its root directory adjusted:
 /// 1. file paths will have `root_prefix` removed
 /// 2. entries with file paths that don't start with `root_prefix` will be omitted
 ///
+/// By default, the prefix must match whole path components: separators and "." components are
+/// skipped in both the prefix and the file paths (so "dir/", "./dir" and "dir//" match "./dir/file"
+/// and "dir//file", and all remove the same prefix). A prefix that would end inside a path component
+/// (e.g.: "test" on "testdir/file.txt") is rejected with an error, unless the prefix ends with a separator
+/// (e.g.: "test/", which just doesn't match "testdir/file.txt"). With `options.raw`, the prefix is
+/// removed as text instead.
+///
 /// On success, returns a Vec of warning strings, if any warnings were emitted while reading the file.
 ///
 /// # Errors
 ///
 /// Any error emitted while reading or writing the files will be returned.
-pub fn change_root(filename: &str, out_filename: &str, root_prefix: &str)
+/// Without `options.raw`, a `ChangeRootError::SplitPathComponent` error will be returned if the prefix
+/// ends inside a path component of any entry (the output file is removed).
+pub fn change_root(filename: &str, out_filename: &str, root_prefix: &str, options: ChangeRootOptions)
     -> Result<ChangeRootSuccess, Box<dyn std::error::Error>> {
 
-    let mut entry_count_before= 0;
-    let mut entry_count_after= 0;
+    let mut entry_count_before: usize = 0;
+    let mut entry_count_after: usize = 0;
 
-    let f = |log_file: &mut LogFile<Vec<LogEntry>>| {
-        entry_count_before = log_file.entries.len();
+    let path_prefix = PathPrefix::new(root_prefix.as_bytes());
+    let prefix_is_empty = if options.raw {root_prefix.is_empty()} else {path_prefix.is_empty()};
 
-        log_file.entries =
-            log_file.entries.iter().filter_map(|log_entry| {
-                log_entry.filename.strip_prefix(root_prefix).map(|new_path| {
-                    LogEntry{
-                        filename: new_path.to_string(),
-                        hashes: log_entry.hashes.clone(),
-                    }
-                })
-            }).collect();
+    let f = |log_entry: LogEntry| -> Result<Option<LogEntry>, Box<dyn std::error::Error>> {
+        entry_count_before += 1;
 
-        entry_count_after = log_file.entries.len();
+        let new_path = if options.raw {
+            log_entry.filename.strip_prefix(root_prefix.as_bytes())
+        }
+        else {
+            match strip_path_prefix(&log_entry.filename, &path_prefix) {
+                PathPrefixMatch::Match(x) => Some(x),
+                PathPrefixMatch::NoMatch => None,
+                PathPrefixMatch::SplitComponent => return Err(ChangeRootError::SplitPathComponent {
+                    prefix: root_prefix.to_string(),
+                    path: String::from_utf8_lossy(&log_entry.filename).into_owned(),
+                }.into()),
+            }
+        };
+        let Some(new_path) = new_path.map(|x| x.to_vec()) else {return Ok(None)};
+
+        entry_count_after += 1;
+        Ok(Some(LogEntry{
+            filename: new_path,
+            ..log_entry
+        }))
     };
 
-    let file_warning_lines = log_ops::process_log(filename, out_filename, f)?;
+    let file_warning_lines = log_ops::process_log_entries(filename, out_filename, f)?;
     let entries_matched = entry_count_after;
     // Safety: this will not overflow, because filtering can only remove entries.
     let entries_omitted = entry_count_before.checked_sub(entry_count_after)
         .expect("filter should not increase entry count");
 
     let info_lines = info_lines(entries_matched, entries_omitted);
-    let warning_lines = warning_lines(entries_matched, entries_omitted, root_prefix);
+    let warning_lines = warning_lines(entries_matched, entries_omitted, prefix_is_empty);
 
     Ok(ChangeRootSuccess{file_warning_lines, info_lines, warning_lines})
 }
@@ -104,10 +230,82 @@ mod test {
             let temp_file = temp_dir.path().join("test.txt");
             let temp_file_path_str = temp_file.to_str().unwrap();
 
-            change_root("tests/test1.txt", temp_file_path_str, "hashdeepComp/").unwrap();
+            change_root("tests/test1.txt", temp_file_path_str, "hashdeepComp/", ChangeRootOptions::default()).unwrap();
 
             let p = predicates::path::eq_file("tests/test1_root_changed.txt");
             assert!(p.eval(temp_file.as_path()));
         }
+        {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let temp_file = temp_dir.path().join("test.txt");
+            let temp_file_path_str = temp_file.to_str().unwrap();
+
+            let err = change_root("tests/test1.txt", temp_file_path_str, "hashdeep", ChangeRootOptions::default()).unwrap_err();
+            assert_eq!(err.to_string(), "prefix \"hashdeep\" ends inside a path component of \"hashdeepComp/345.txt\" (to remove it as text, use --raw)");
+            assert!(!temp_file.exists());
+
+            change_root("tests/test1.txt", temp_file_path_str, "hashdeep", ChangeRootOptions{raw: true}).unwrap();
+            assert!(std::fs::read_to_string(&temp_file).unwrap().contains(",Comp/345.txt\n"));
+        }
+        {
+            //a sibling directory whose name starts with the prefix's directory name
+            let temp_dir = tempfile::tempdir().unwrap();
+            let log_file = temp_dir.path().join("log.txt");
+            let log_file_path_str = log_file.to_str().unwrap();
+            std::fs::write(&log_file, concat!(
+                "%%%% HASHDEEP-1.0\n%%%% size,md5,sha256,filename\n## Invoked from: /home/user\n## $ hashdeep -lr .\n## \n",
+                "1,0cc175b9c0f1b6a831c399e269772661,ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb,data/a.txt\n",
+                "1,92eb5ffee6ae2fec3ad71c777531578f,3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d,data_backup/a.txt\n",
+            )).unwrap();
+
+            let temp_file = temp_dir.path().join("test.txt");
+            let temp_file_path_str = temp_file.to_str().unwrap();
+
+            let success = change_root(log_file_path_str, temp_file_path_str, "data/", ChangeRootOptions::default()).unwrap();
+            assert_eq!(success.info_lines, info_lines(1, 1));
+            let output = std::fs::read_to_string(&temp_file).unwrap();
+            assert!(output.ends_with(",0cc175b9c0f1b6a831c399e269772661,ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb,a.txt\n"));
+            std::fs::remove_file(&temp_file).unwrap();
+
+            //without a trailing separator, the prefix is ambiguous
+            let err = change_root(log_file_path_str, temp_file_path_str, "data", ChangeRootOptions::default()).unwrap_err();
+            assert_eq!(err.to_string(), "prefix \"data\" ends inside a path component of \"data_backup/a.txt\" (to remove it as text, use --raw)");
+        }
+    }
+
+    #[test]
+    fn strip_path_prefix_test() {
+        use PathPrefixMatch::*;
+        let strip = |path: &'static str, prefix: &'static str| strip_path_prefix(path.as_bytes(), &PathPrefix::new(prefix.as_bytes()));
+
+        for prefix in ["dir", "dir/", "./dir", "dir//", "./dir/./", "dir/."] {
+            assert_eq!(strip("dir/file", prefix), Match(b"file"));
+            assert_eq!(strip("./dir/file", prefix), Match(b"file"));
+            assert_eq!(strip("dir//./file", prefix), Match(b"file"));
+            assert_eq!(strip("dir/sub//file", prefix), Match(b"sub//file"));
+            assert_eq!(strip("other/dir/file", prefix), NoMatch);
+            assert_eq!(strip("/dir/file", prefix), NoMatch);
+            assert_eq!(strip("dir", prefix), NoMatch);
+        }
+
+        //only a prefix without a trailing separator can end inside a path component
+        for prefix in ["dir", "./dir"] {
+            assert_eq!(strip("dirt/file", prefix), SplitComponent);
+        }
+        for prefix in ["dir/", "dir//", "./dir/./", "dir/."] {
+            assert_eq!(strip("dirt/file", prefix), NoMatch);
+        }
+
+        assert_eq!(strip("a/b/c", "a/b"), Match(b"c"));
+        assert_eq!(strip("a/bc/d", "a/b"), SplitComponent);
+        assert_eq!(strip("a/bc/d", "a/b/"), NoMatch);
+        assert_eq!(strip("ab/c/d", "a/c"), NoMatch);
+        assert_eq!(strip("/home/user/file", "/home//user/"), Match(b"file"));
+        assert_eq!(strip("/home/user/file", "/"), Match(b"home/user/file"));
+        assert_eq!(strip("home/user/file", "/home"), NoMatch);
+        assert_eq!(strip("./.hidden/file", "."), Match(b"./.hidden/file"));
+        assert_eq!(strip(".hidden/file", ".hid"), SplitComponent);
+        assert_eq!(strip("./a", ""), Match(b"./a"));
+        assert_eq!(strip_path_prefix(b"\xff/a", &PathPrefix::new(b"\xff")), Match(b"a"));
     }
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+//! Detecting logs whose file paths have different roots (e.g.: one log made from `/home/user` with
+//! `hashdeep -lr data/`, the other made from inside `data/`), so their entries can be aligned before partitioning
+
+use std::collections::HashMap;
+
+use crate::log_entry::LogEntry;
+use crate::partitioner::match_pair::MatchPair;
+use crate::partitioner::MatchPartition;
+
+/// A consistent prefix difference between the file paths of two logs
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct RootAlignment {
+    /// The prefix of file 1's paths (may be empty)
+    pub prefix1: Vec<u8>,
+    /// The prefix of file 2's paths (may be empty)
+    pub prefix2: Vec<u8>,
+    /// The number of hashes match pairs whose paths differ only by these prefixes
+    pub matching_pairs: usize,
+    /// The total number of hashes match pairs
+    pub total_pairs: usize,
+    /// True if the logs' "## Invoked from:" header lines agree that the prefixes lead to the same directory
+    pub headers_agree: bool,
+    /// If the prefixes were removed, the number of entries they were removed from, in file 1 and file 2
+    pub applied: Option<(usize, usize)>,
+}
+
+/// Formats a prefix for printing.
+fn printable(prefix: &[u8]) -> String {
+    if prefix.is_empty() {
+        "(none)".to_string()
+    }
+    else {
+        format!("\"{}\"", String::from_utf8_lossy(prefix))
+    }
+}
+
+impl RootAlignment {
+
+    /// Gets printable lines that describe the alignment (as a suggestion, or as applied).
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut v = vec![];
+        let pairs = format!("{} of {} hashes match pairs", self.matching_pairs, self.total_pairs);
+
+        match self.applied {
+            None => {
+                v.push(format!("The logs' file paths appear to have different roots ({pairs} differ only by a prefix):"));
+                v.push(format!("  file 1 prefix: {}", printable(&self.prefix1)));
+                v.push(format!("  file 2 prefix: {}", printable(&self.prefix2)));
+            },
+            Some((count1, count2)) => {
+                v.push(format!("The logs' roots were aligned ({pairs} differed only by a prefix):"));
+                for (file_number, prefix, count) in [(1, &self.prefix1, count1), (2, &self.prefix2, count2)] {
+                    if !prefix.is_empty() {
+                        v.push(format!("  {} was removed from {count} file {file_number} paths", printable(prefix)));
+                    }
+                }
+            },
+        }
+        if self.headers_agree {
+            v.push("  (consistent with the logs' \"## Invoked from:\" header lines)".to_string());
+        }
+        if self.applied.is_none() {
+            v.push("  To compare the logs with these prefixes removed, use --align-roots".to_string());
+        }
+        v
+    }
+}
+
+/// Splits 2 paths that end with the same path components (at least 1) into their differing prefixes.
+///
+/// Returns None if the paths' last components differ.
+pub(crate) fn prefix_difference<'a>(path1: &'a [u8], path2: &'a [u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let common_suffix_len = path1.split(|&c| c == b'/').rev()
+        .zip(path2.split(|&c| c == b'/').rev())
+        .take_while(|(x, y)| x == y)
+        //+1 for each component's separator
+        .map(|(x, _)| x.len() + 1)
+        .sum::<usize>();
+
+    //the common suffix's length includes 1 separator too many (if it is all of either path, there's no separator)
+    let suffix_len = common_suffix_len.checked_sub(1)?;
+    if suffix_len == 0 {
+        return None;
+    }
+    Some((&path1[..path1.len() - suffix_len], &path2[..path2.len() - suffix_len]))
+}
+
+/// Gets the directory a log's relative paths are relative to, from its "## Invoked from:" header line
+/// (without the host name that `hash --relative` logs include).
+fn invocation_directory(header_lines: &[Vec<u8>]) -> Option<&[u8]> {
+    let line = header_lines.iter().find_map(|x| x.strip_prefix(b"## Invoked from: "))?;
+    let end = line.windows(b" (host: ".len()).position(|x| x == b" (host: ").unwrap_or(line.len());
+    Some(&line[..end])
+}
+
+/// Gets a path's components, without empty and "." components.
+fn normalized_components(path: &[u8]) -> Vec<&[u8]> {
+    path.split(|&c| c == b'/').filter(|x| !x.is_empty() && *x != b".").collect()
+}
+
+/// True if both prefixes (relative to their logs' invocation directories) refer to the same directory.
+fn headers_agree(prefix1: &[u8], header_lines1: &[Vec<u8>], prefix2: &[u8], header_lines2: &[Vec<u8>]) -> bool {
+    if prefix1.starts_with(b"/") || prefix2.starts_with(b"/") {
+        return false;
+    }
+    let (Some(dir1), Some(dir2)) = (invocation_directory(header_lines1), invocation_directory(header_lines2)) else {
+        return false;
+    };
+    let full_path = |dir: &[u8], prefix: &[u8]| normalized_components(&[dir, b"/", prefix].concat()).join(b"/".as_slice());
+
+    dir1.starts_with(b"/") && dir2.starts_with(b"/") && full_path(dir1, prefix1) == full_path(dir2, prefix2)
+}
+
+/// Looks for a consistent prefix difference between the paths of the hashes match pairs of a partition.
+///
+/// Hashes match pairs are entries with the same contents but different paths: if most of them differ only by
+/// the same prefixes (and they outnumber the full match pairs), the logs' paths probably have different roots.
+/// The logs' header lines are checked for agreement.
+pub(crate) fn detect_root_alignment(
+    mp: &MatchPartition,
+    header_lines1: &[Vec<u8>],
+    header_lines2: &[Vec<u8>],
+) -> Option<RootAlignment> {
+
+    let mut counts = HashMap::<(&[u8], &[u8]), usize>::new();
+    for MatchPair{from_file1, from_file2} in &mp.hashes_match_pairs {
+        if let Some(prefixes) = prefix_difference(&from_file1.filename, &from_file2.filename) {
+            *counts.entry(prefixes).or_default() += 1;
+        }
+    }
+
+    //ties are broken by the prefixes, so the result doesn't depend on the entries' order
+    let ((prefix1, prefix2), matching_pairs) = counts.into_iter()
+        .max_by(|(k1, v1), (k2, v2)| v1.cmp(v2).then_with(|| k2.cmp(k1)))?;
+
+    let total_pairs = mp.hashes_match_pairs.len();
+    if matching_pairs * 2 <= total_pairs || matching_pairs <= mp.full_match_pairs.len() {
+        return None;
+    }
+
+    Some(RootAlignment {
+        prefix1: prefix1.to_vec(),
+        prefix2: prefix2.to_vec(),
+        matching_pairs,
+        total_pairs,
+        headers_agree: headers_agree(prefix1, header_lines1, prefix2, header_lines2),
+        applied: None,
+    })
+}
+
+/// Removes a prefix (as text) from the paths of the entries that start with it.
+///
+/// Returns the number of entries that the prefix was removed from.
+pub(crate) fn remove_prefix(entries: &mut [LogEntry], prefix: &[u8]) -> usize {
+    if prefix.is_empty() {
+        return 0;
+    }
+
+    let mut count = 0;
+    for entry in entries {
+        if let Some(rest) = entry.filename.strip_prefix(prefix) {
+            entry.filename = rest.to_vec();
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prefix_difference_test() {
+        assert_eq!(prefix_difference(b"data/a/b.txt", b"./a/b.txt"), Some((b"data/".as_slice(), b"./".as_slice())));
+        assert_eq!(prefix_difference(b"data/a/b.txt", b"a/b.txt"), Some((b"data/".as_slice(), b"".as_slice())));
+        assert_eq!(prefix_difference(b"/home/user/b", b"/mnt/b"), Some((b"/home/user/".as_slice(), b"/mnt/".as_slice())));
+        assert_eq!(prefix_difference(b"x/b.txt", b"x/c.txt"), None);
+        assert_eq!(prefix_difference(b"xb.txt", b"b.txt"), None);
+    }
+
+    #[test]
+    fn invocation_directory_test() {
+        let header = |line: &str| vec![b"%%%% HASHDEEP-1.0".to_vec(), line.as_bytes().to_vec()];
+        assert_eq!(invocation_directory(&header("## Invoked from: /home/user")), Some(b"/home/user".as_slice()));
+        assert_eq!(invocation_directory(&header("## Invoked from: /mnt/volume (host: h)")), Some(b"/mnt/volume".as_slice()));
+        assert_eq!(invocation_directory(&header("## $ hashdeep -lr .")), None);
+    }
+
+    #[test]
+    fn headers_agree_test() {
+        let header = |dir: &str| vec![format!("## Invoked from: {dir}").into_bytes()];
+        assert!(headers_agree(b"data/", &header("/home/user"), b"./", &header("/home/user/data (host: h)")));
+        assert!(headers_agree(b"a/b/", &header("/"), b"", &header("/a/b/")));
+        assert!(!headers_agree(b"data/", &header("/home/user"), b"./", &header("/home/other/data")));
+        assert!(!headers_agree(b"data/", &header("/home/user"), b"./", &[]));
+    }
+}
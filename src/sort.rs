@@ -1,29 +1,149 @@
-use crate::common::LogFile;
+use std::cmp::Ordering;
+
 use crate::log_entry::LogEntry;
 use crate::log_ops;
 
-/// Reads a hashdeep log file and writes its entries to a new file, sorted by name.
+/// The ordering of `sort_log` output
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum SortKey {
+    /// By file path, as bytes
+    #[default]
+    Name,
+    /// By hashes, so entries with the same content are adjacent
+    Hashes,
+    /// By size, largest first
+    Size,
+    /// By file path, compared 1 path component at a time (e.g.: "a/b" before "a.b/c")
+    Path,
+    /// By file path, with runs of digits compared as numbers (e.g.: "file2" before "file10")
+    Natural,
+}
+
+/// An order for log entries: a sort key, plus a fixed tiebreaker order
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct SortOrder {
+    pub key: SortKey,
+    /// If true, the key's order is reversed (ties are still broken in the same order)
+    pub reverse: bool,
+}
+
+/// Settings for `sort_log`
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SortOptions {
+    pub order: SortOrder,
+    /// The estimated memory (in bytes) that log entries may use: larger logs are sorted in parts,
+    /// using temporary files
+    pub memory_budget: usize,
+    /// The directory that temporary files are created in (if None, the system's temporary directory)
+    pub temp_directory: Option<String>,
+}
+
+impl Default for SortOptions {
+
+    /// Sorts by name, in up to 1 GiB of memory.
+    fn default() -> Self {
+        SortOptions{order: SortOrder::default(), memory_budget: 1 << 30, temp_directory: None}
+    }
+}
+
+/// Reads a hashdeep log file and writes its entries to a new file, sorted as specified by `options`.
+///
+/// Entries that are equal by `options.order.key` are ordered by file path (as bytes), then by hashes,
+/// so the output order doesn't depend on the input order.
+///
+/// Logs whose entries would use more than `options.memory_budget` bytes are sorted with an external
+/// merge sort (see `log_ops::process_log_sorted`): the output file is identical.
 ///
 /// On success, returns a Vec of warning strings, if any warnings were emitted while reading the file.
 ///
 /// # Errors
 ///
 /// Any error emitted while reading or writing the files will be returned.
-pub fn sort_log(filename: &str, out_filename: &str) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>>{
+pub fn sort_log(filename: &str, out_filename: &str, options: &SortOptions) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>>{
+
+    log_ops::process_log_sorted(
+        filename,
+        out_filename,
+        |v1, v2| compare_entries(v1, v2, options.order),
+        options.memory_budget,
+        options.temp_directory.as_deref(),
+    )
+}
+
+/// Sorts log entries by name (the default order of `sort_log`).
+pub(crate) fn sort_entries(entries: &mut [LogEntry]) {
+    sort_entries_by(entries, SortOrder::default());
+}
+
+/// Sorts log entries in `order` (as `sort_log` does).
+pub(crate) fn sort_entries_by(entries: &mut [LogEntry], order: SortOrder) {
+    entries.sort_by(|v1, v2| compare_entries(v1, v2, order));
+}
+
+/// Compares log entries in `order` (a total order: only identical entries are equal).
+fn compare_entries(v1: &LogEntry, v2: &LogEntry, order: SortOrder) -> Ordering {
+    let key_order = match order.key {
+        SortKey::Name => v1.filename.cmp(&v2.filename),
+        SortKey::Hashes => v1.digests.cmp(&v2.digests).then(v1.size.cmp(&v2.size)),
+        SortKey::Size => v2.size.cmp(&v1.size),
+        SortKey::Path => path_components(&v1.filename).cmp(path_components(&v2.filename)),
+        SortKey::Natural => natural_cmp(&v1.filename, &v2.filename),
+    };
+    let key_order = if order.reverse {key_order.reverse()} else {key_order};
+
+    key_order
+        .then_with(|| v1.filename.cmp(&v2.filename))
+        .then_with(|| v1.cmp(v2))
+}
+
+fn path_components(filename: &[u8]) -> impl Iterator<Item = &[u8]> {
+    filename.split(|&c| c == b'/')
+}
+
+/// Compares file paths in natural order: runs of ASCII digits are compared by numeric value,
+/// and other bytes are compared as bytes.
+///
+/// Numbers with leading zeros are equal to the same numbers without them (e.g.: "file01" and "file1").
+fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
 
-    fn sort_entries(log_file: &mut LogFile<Vec<LogEntry>>) {
-        log_file.entries.sort_by(|v1, v2| {
-            v1.filename.cmp(&v2.filename)
-        });
+    //splits off a leading run of digits, without its leading zeros
+    fn split_number(s: &[u8]) -> (&[u8], &[u8]) {
+        let end = s.iter().position(|c| !c.is_ascii_digit()).unwrap_or(s.len());
+        let start = s[..end].iter().position(|&c| c != b'0').unwrap_or(end);
+        (&s[start..end], &s[end..])
     }
 
-    log_ops::process_log(filename, out_filename, sort_entries)
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (number_a, rest_a) = split_number(a);
+                let (number_b, rest_b) = split_number(b);
+                //without leading zeros, a longer number is larger
+                let order = number_a.len().cmp(&number_b.len()).then(number_a.cmp(number_b));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a, b) = (rest_a, rest_b);
+            },
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::digest::HashAlgorithm;
     use predicates::prelude::*;
 
     #[test]
@@ -33,10 +153,112 @@ mod test {
             let temp_file = temp_dir.path().join("test1 sorted.txt");
             let temp_file_path_str = temp_file.to_str().unwrap();
 
-            sort_log("tests/test1.txt", temp_file_path_str).unwrap();
+            sort_log("tests/test1.txt", temp_file_path_str, &SortOptions::default()).unwrap();
 
             let p = predicates::path::eq_file("tests/test1 sorted.txt");
             assert!(p.eval(temp_file.as_path()));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sort_log_external_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let inputs = [
+            "tests/test1.txt",
+            "tests/sort_files/test1_invalid_log_entry.txt",
+            "tests/sort_files/test1_non_utf8_filenames.txt",
+            "tests/sort_files/test_sort_keys.txt",
+        ];
+        for (i, input) in inputs.iter().enumerate() {
+            for key in [SortKey::Name, SortKey::Hashes, SortKey::Size, SortKey::Natural] {
+                let sorted = |name: &str, memory_budget: usize| {
+                    let path = temp_dir.path().join(format!("{i}_{key:?}_{name}"));
+                    let options = SortOptions {
+                        order: SortOrder{key, reverse: false},
+                        memory_budget,
+                        temp_directory: Some(temp_dir.path().to_str().unwrap().to_string()),
+                    };
+                    let warnings = sort_log(input, path.to_str().unwrap(), &options).unwrap();
+                    (std::fs::read(path).unwrap(), warnings)
+                };
+
+                //with a budget of 1 byte, every entry is spilled to a run of its own
+                assert_eq!(sorted("external", 1), sorted("in_memory", SortOptions::default().memory_budget));
+                assert_eq!(sorted("external_2", 200), sorted("in_memory_2", usize::MAX));
+            }
+        }
+
+        //temporary files are removed
+        let mut leftovers = std::fs::read_dir(temp_dir.path()).unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .filter(|x| x.starts_with("hashdeep-compare-sort"));
+        assert_eq!(leftovers.next(), None);
+    }
+
+    fn sorted_filenames(filenames: &[&str], key: SortKey, reverse: bool) -> Vec<String> {
+        let mut entries = filenames.iter()
+            .map(|x| LogEntry{size: 0, digests: vec![], filename: x.as_bytes().to_vec()})
+            .collect::<Vec<_>>();
+        sort_entries_by(&mut entries, SortOrder{key, reverse});
+        entries.into_iter().map(|x| String::from_utf8(x.filename).unwrap()).collect()
+    }
+
+    #[test]
+    fn sort_entries_by_path_test() {
+        let filenames = ["a.b/c", "a/b", "a-b", "a/b/c", "a"];
+        assert_eq!(sorted_filenames(&filenames, SortKey::Name, false), ["a", "a-b", "a.b/c", "a/b", "a/b/c"]);
+        assert_eq!(sorted_filenames(&filenames, SortKey::Path, false), ["a", "a/b", "a/b/c", "a-b", "a.b/c"]);
+        assert_eq!(sorted_filenames(&filenames, SortKey::Path, true), ["a.b/c", "a-b", "a/b/c", "a/b", "a"]);
+    }
+
+    #[test]
+    fn sort_entries_by_natural_test() {
+        let filenames = ["file10", "file2", "file1", "file01", "file", "file2a", "file2.txt", "10", "9"];
+        assert_eq!(sorted_filenames(&filenames, SortKey::Natural, false),
+                   ["9", "10", "file", "file01", "file1", "file2", "file2.txt", "file2a", "file10"]);
+        assert_eq!(sorted_filenames(&filenames, SortKey::Natural, true),
+                   ["file10", "file2a", "file2.txt", "file2", "file01", "file1", "file", "10", "9"]);
+    }
+
+    #[test]
+    fn natural_cmp_test() {
+        assert_eq!(natural_cmp(b"file2", b"file10"), Ordering::Less);
+        assert_eq!(natural_cmp(b"file002", b"file10"), Ordering::Less);
+        assert_eq!(natural_cmp(b"file01", b"file1"), Ordering::Equal);
+        assert_eq!(natural_cmp(b"a0", b"a"), Ordering::Greater);
+        assert_eq!(natural_cmp(b"a00", b"a0"), Ordering::Equal);
+        assert_eq!(natural_cmp(b"99999999999999999999999", b"100000000000000000000000"), Ordering::Less);
+        assert_eq!(natural_cmp(b"", b""), Ordering::Equal);
+        assert_eq!(natural_cmp(b"\xff1", b"\xff2"), Ordering::Less);
+    }
+
+    #[test]
+    fn sort_entries_by_content_test() {
+        let entry = |size: u64, md5: &str, filename: &str| {
+            LogEntry::from_bytes(format!("{size},{md5},{filename}").as_bytes(), &[HashAlgorithm::Md5]).unwrap()
+        };
+        let entries = vec![
+            entry(1, "cccccccccccccccccccccccccccccccc", "b"),
+            entry(3, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "d"),
+            entry(1, "cccccccccccccccccccccccccccccccc", "a"),
+            entry(2, "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "c"),
+            entry(3, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "a"),
+        ];
+        let sorted = |key: SortKey, reverse: bool| -> Vec<(u64, String)> {
+            let mut entries = entries.clone();
+            sort_entries_by(&mut entries, SortOrder{key, reverse});
+            entries.into_iter().map(|x| (x.size, String::from_utf8(x.filename).unwrap())).collect()
+        };
+        let expected = |x: &[(u64, &str)]| -> Vec<(u64, String)> {
+            x.iter().map(|(size, filename)| (*size, filename.to_string())).collect()
+        };
+
+        //ties are broken by filename (ascending, even when reversed)
+        assert_eq!(sorted(SortKey::Hashes, false), expected(&[(2, "c"), (3, "a"), (3, "d"), (1, "a"), (1, "b")]));
+        assert_eq!(sorted(SortKey::Hashes, true), expected(&[(1, "a"), (1, "b"), (3, "a"), (3, "d"), (2, "c")]));
+        assert_eq!(sorted(SortKey::Size, false), expected(&[(3, "a"), (3, "d"), (2, "c"), (1, "a"), (1, "b")]));
+        assert_eq!(sorted(SortKey::Size, true), expected(&[(1, "a"), (1, "b"), (2, "c"), (3, "a"), (3, "d")]));
+        //then by hashes
+        assert_eq!(sorted(SortKey::Name, false), expected(&[(1, "a"), (3, "a"), (1, "b"), (2, "c"), (3, "d")]));
+    }
+}
@@ -0,0 +1,331 @@
+//! Spot-checking a hashdeep log, by rehashing a reproducible random sample of its files
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::command;
+use crate::common;
+use crate::digest::HashAlgorithm;
+use crate::log_entry::LogEntry;
+use crate::native_hash::{self, NativeHashOptions};
+
+/// How much of a log to sample
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SampleSize {
+    /// A percentage of the log's entries (rounded up to a whole entry)
+    Percent(f64),
+    /// A maximum total size (in bytes, as logged) of the sampled files
+    Bytes(u64),
+}
+
+/// Settings for `verify_log`
+#[derive(Clone, PartialEq, Debug)]
+pub struct VerifyOptions {
+    /// How much of the log to sample
+    pub sample_size: SampleSize,
+    /// The random seed that selects the sample: the same seed, log and sample size select the same sample
+    pub seed: u64,
+    /// The directory that relative logged paths are resolved from (if None, the current directory)
+    pub base_directory: Option<String>,
+    /// The number of worker threads that hash files
+    pub jobs: NonZeroUsize,
+    /// The maximum number of files that may be read at the same time (across all workers)
+    pub max_concurrent_reads: NonZeroUsize,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct VerifySuccess
+{
+    /// Printable warning lines about the hashdeep log file, if any were emitted
+    pub log_warning_lines: Option<Vec<String>>,
+    /// Printable statistics about the verification results
+    pub stats_string: String,
+    /// Printable lines describing each sampled file that didn't match its log entry
+    pub problem_lines: Vec<String>,
+}
+
+impl VerifySuccess {
+
+    /// True if any sampled file didn't match its log entry (or couldn't be checked).
+    pub fn problems_found(&self) -> bool {
+        !self.problem_lines.is_empty()
+    }
+}
+
+/// Parses a --percent argument: a number greater than 0 and at most 100.
+pub fn parse_percent(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(x) if x > 0.0 && x <= 100.0 => Ok(x),
+        _ => Err(format!("\"{s}\" is not a percentage greater than 0 and at most 100")),
+    }
+}
+
+/// Gets a seed from the current time, for runs that don't specify one.
+pub fn seed_from_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0)
+}
+
+/// A small pseudorandom number generator (SplitMix64).
+///
+/// It's implemented here (instead of using a library generator) so that a seed selects the same
+/// sample in every version of hashdeep-compare.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Gets a number in 0..n (n > 0).
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/// Selects a random sample of log entries, returning their indices in ascending order.
+///
+/// Entries are shuffled (as determined by `seed`), then taken in shuffled order: for `SampleSize::Bytes`,
+/// entries that would exceed the remaining budget are passed over.
+fn sample_indices(entries: &[LogEntry], sample_size: SampleSize, seed: u64) -> Vec<usize> {
+
+    let mut rng = SplitMix64(seed);
+    let mut shuffled = (0..entries.len()).collect::<Vec<usize>>();
+    for i in (1..shuffled.len()).rev() {
+        shuffled.swap(i, rng.below(i + 1));
+    }
+
+    let mut sample = match sample_size {
+        SampleSize::Percent(percent) => {
+            let count = (entries.len() as f64 * percent / 100.0).ceil() as usize;
+            shuffled.truncate(count.min(entries.len()));
+            shuffled
+        },
+        SampleSize::Bytes(budget) => {
+            let mut remaining = budget;
+            shuffled.into_iter()
+                .filter(|&i| {
+                    let fits = entries[i].size <= remaining;
+                    if fits {
+                        remaining -= entries[i].size;
+                    }
+                    fits
+                })
+                .collect()
+        },
+    };
+    sample.sort_unstable();
+    sample
+}
+
+/// The result of checking a sampled file against its log entry
+enum FileCheck {
+    Verified,
+    ChangedContents,
+    ChangedSize(u64),
+    Missing,
+    Unreadable(std::io::Error),
+}
+
+/// Rehashes a random sample of a hashdeep log's files, and compares them to their log entries.
+///
+/// The sample is selected by `options.sample_size` and `options.seed` (see `SampleSize`).
+/// Files are found at their logged paths (relative paths are resolved from `options.base_directory`),
+/// and are hashed with the built-in hashing engine, using the log's hash columns.
+/// A file whose size has changed isn't read.
+///
+/// On success, returns a statistics string, and a line for each sampled file that is missing,
+/// can't be read, or doesn't match its log entry, plus warning strings if any were emitted
+/// while loading the hashdeep log.
+///
+/// # Errors
+///
+/// Any error emitted while reading the log will be returned.
+pub fn verify_log(log_filename: &str, options: &VerifyOptions) -> Result<VerifySuccess, Box<dyn std::error::Error>> {
+
+    let log_file = common::read_log_entries_from_file::<Vec<LogEntry>>(log_filename)?;
+    let entries = &log_file.entries;
+
+    let sample = sample_indices(entries, options.sample_size, options.seed);
+
+    let base_directory = options.base_directory.as_deref().map_or(Path::new(""), Path::new);
+    let path_of = |entry: &LogEntry| -> PathBuf { base_directory.join(command::path_from_bytes(&entry.filename)) };
+
+    //check sizes before reading any files
+    let mut checks = Vec::with_capacity(sample.len());
+    let mut paths_to_hash = vec![];
+    for &i in &sample {
+        let path = path_of(&entries[i]);
+        let check = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() != entries[i].size => Some(FileCheck::ChangedSize(metadata.len())),
+            Ok(_) => None,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(FileCheck::Missing),
+            Err(e) => Some(FileCheck::Unreadable(e)),
+        };
+        if check.is_none() {
+            paths_to_hash.push(path);
+        }
+        checks.push(check);
+    }
+
+    let hash_options = NativeHashOptions {
+        algorithms: log_file.hash_columns.clone(),
+        jobs: options.jobs,
+        max_concurrent_reads: options.max_concurrent_reads,
+        relative_paths: false,
+        save_mtimes: false,
+        previous_log: None,
+    };
+    let mut hash_results = native_hash::hash_files_in_order(&paths_to_hash, &hash_options).into_iter();
+
+    let checks = sample.iter().zip(checks).map(|(&i, check)| {
+        let check = check.unwrap_or_else(|| {
+            match hash_results.next().expect("each unchecked file should have a hash result") {
+                Ok(found) if found.size != entries[i].size => FileCheck::ChangedSize(found.size),
+                Ok(found) if found.digests != entries[i].digests => FileCheck::ChangedContents,
+                Ok(_) => FileCheck::Verified,
+                Err(e) => FileCheck::Unreadable(e),
+            }
+        });
+        (&entries[i], check)
+    }).collect::<Vec<_>>();
+
+    let count = |f: fn(&FileCheck) -> bool| checks.iter().filter(|(_, check)| f(check)).count();
+
+    let mut stats_string = String::new();
+    stats_string.push_str("log verification statistics:\n");
+    stats_string.push_str(format!("   (sample seed: {})\n", options.seed).as_str());
+    stats_string.push_str(format!("   (hashes compared: size,{})\n", HashAlgorithm::names(&log_file.hash_columns)).as_str());
+    stats_string.push_str(format!(" {} of {} log entries sampled ({} of {} bytes)\n",
+        checks.len(), entries.len(),
+        checks.iter().map(|(entry, _)| entry.size).sum::<u64>(),
+        entries.iter().map(|entry| entry.size).sum::<u64>()).as_str());
+    stats_string.push_str(format!(" {} files verified\n", count(|x| matches!(x, FileCheck::Verified))).as_str());
+    stats_string.push_str(format!(" {} files with changed contents\n", count(|x| matches!(x, FileCheck::ChangedContents))).as_str());
+    stats_string.push_str(format!(" {} files with changed size\n", count(|x| matches!(x, FileCheck::ChangedSize(_)))).as_str());
+    stats_string.push_str(format!(" {} files missing\n", count(|x| matches!(x, FileCheck::Missing))).as_str());
+    stats_string.push_str(format!(" {} files could not be read\n", count(|x| matches!(x, FileCheck::Unreadable(_)))).as_str());
+
+    let problem_lines = checks.iter().filter_map(|(entry, check)| {
+        let filename = String::from_utf8_lossy(&entry.filename);
+        match check {
+            FileCheck::Verified => None,
+            FileCheck::ChangedContents => Some(format!("changed contents: {filename}")),
+            FileCheck::ChangedSize(size) => Some(format!("changed size: {filename} (logged: {} bytes, found: {size} bytes)", entry.size)),
+            FileCheck::Missing => Some(format!("missing: {filename}")),
+            FileCheck::Unreadable(e) => Some(format!("could not be read: {filename} ({e})")),
+        }
+    }).collect();
+
+    Ok(VerifySuccess {
+        log_warning_lines: log_file.warning_report(),
+        stats_string,
+        problem_lines,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries_with_sizes(sizes: &[u64]) -> Vec<LogEntry> {
+        sizes.iter().enumerate()
+            .map(|(i, &size)| LogEntry{size, digests: vec![], filename: format!("file{i}").into_bytes()})
+            .collect()
+    }
+
+    #[test]
+    fn parse_percent_test() {
+        assert_eq!(parse_percent("100"), Ok(100.0));
+        assert_eq!(parse_percent("0.5"), Ok(0.5));
+        assert!(parse_percent("0").is_err());
+        assert!(parse_percent("100.1").is_err());
+        assert!(parse_percent("-5").is_err());
+        assert!(parse_percent("NaN").is_err());
+        assert!(parse_percent("five").is_err());
+    }
+
+    #[test]
+    fn split_mix_64_test() {
+        //reference values for seed 1234567
+        let mut rng = SplitMix64(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+        assert_eq!(rng.next_u64(), 9817491932198370423);
+    }
+
+    #[test]
+    fn sample_indices_test() {
+        let entries = entries_with_sizes(&[10, 20, 30, 40, 50, 60, 70, 80, 90, 100]);
+
+        //reproducible
+        let sample = sample_indices(&entries, SampleSize::Percent(30.0), 42);
+        assert_eq!(sample, sample_indices(&entries, SampleSize::Percent(30.0), 42));
+        assert_eq!(sample.len(), 3);
+        assert!(sample.windows(2).all(|x| x[0] < x[1]));
+
+        //rounded up
+        assert_eq!(sample_indices(&entries, SampleSize::Percent(0.1), 42).len(), 1);
+        assert_eq!(sample_indices(&entries, SampleSize::Percent(100.0), 42), (0..10).collect::<Vec<_>>());
+        assert_eq!(sample_indices(&[], SampleSize::Percent(100.0), 42), Vec::<usize>::new());
+
+        //different seeds select different samples (for these seeds)
+        assert_ne!(sample_indices(&entries, SampleSize::Percent(50.0), 1), sample_indices(&entries, SampleSize::Percent(50.0), 2));
+
+        //byte budgets
+        for seed in 0..20 {
+            let sample = sample_indices(&entries, SampleSize::Bytes(100), seed);
+            assert!(!sample.is_empty());
+            assert!(sample.iter().map(|&i| entries[i].size).sum::<u64>() <= 100);
+        }
+        assert_eq!(sample_indices(&entries, SampleSize::Bytes(5), 42), Vec::<usize>::new());
+        assert_eq!(sample_indices(&entries, SampleSize::Bytes(550), 42).len(), 10);
+    }
+
+    #[test]
+    fn verify_log_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("unchanged"), "1").unwrap();
+        std::fs::write(temp_dir.path().join("changed"), "2").unwrap();
+        std::fs::write(temp_dir.path().join("resized"), "33").unwrap();
+
+        let log = temp_dir.path().join("log");
+        std::fs::write(&log, concat!(
+            "%%%% HASHDEEP-1.0\n",
+            "%%%% size,md5,filename\n",
+            "## Invoked from: /\n",
+            "## $ hashdeep -c md5 -l -r -o f .\n",
+            "## \n",
+            "1,c4ca4238a0b923820dcc509a6f75849b,unchanged\n",
+            "1,eccbc87e4b5ce2fe28308fd9f2a7baf3,changed\n",
+            "1,eccbc87e4b5ce2fe28308fd9f2a7baf3,resized\n",
+            "1,a87ff679a2f3e71d9181a67b7542122c,deleted\n",
+        )).unwrap();
+
+        let options = VerifyOptions {
+            sample_size: SampleSize::Percent(100.0),
+            seed: 0,
+            base_directory: Some(temp_dir.path().to_str().unwrap().to_string()),
+            jobs: NonZeroUsize::new(2).unwrap(),
+            max_concurrent_reads: NonZeroUsize::new(1).unwrap(),
+        };
+        let success = verify_log(log.to_str().unwrap(), &options).unwrap();
+
+        assert_eq!(success.problem_lines, vec![
+            "changed contents: changed",
+            "changed size: resized (logged: 1 bytes, found: 2 bytes)",
+            "missing: deleted",
+        ]);
+        assert!(success.problems_found());
+        assert!(success.stats_string.contains(" 4 of 4 log entries sampled (4 of 4 bytes)\n 1 files verified\n"));
+
+        let options = VerifyOptions{sample_size: SampleSize::Bytes(2), ..options};
+        let success = verify_log(log.to_str().unwrap(), &options).unwrap();
+        assert!(success.stats_string.contains(" 2 of 4 log entries sampled (2 of 4 bytes)\n"));
+    }
+}
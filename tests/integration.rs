@@ -22,7 +22,7 @@
 //! - stdout: a file containing the program's stdout output
 //! - stderr: a file containing the program's stderr output
 //! - exitcode: a file containing the program's exit code, as a string representation of an
-//! Option\<i32\>
+//!   Option\<i32\>
 //! - outfiles: a directory containing the files created by the program in its working directory
 //!
 //! Any file or directory which would be empty (e.g.: stderr after a run with no errors)
@@ -135,9 +135,13 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
     run_test("help/no_subcommand",          &["help"])?;
     run_test("help/nonexistent_subcommand", &["help", "nonexistent_subcommand"])?;
     run_test("help/hash",                   &["help", "hash"])?;
+    run_test("help/hash_archive",           &["help", "hash-archive"])?;
     run_test("help/sort",                   &["help", "sort"])?;
     run_test("help/root",                   &["help", "root"])?;
+    run_test("help/rewrite",                &["help", "rewrite"])?;
     run_test("help/part",                   &["help", "part"])?;
+    run_test("help/audit",                  &["help", "audit"])?;
+    run_test("help/verify",                 &["help", "verify"])?;
     run_test("help/extra_argument",         &["help", "part", "extra"])?;
 
 
@@ -187,6 +191,137 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
         remove_hashdeep_log_header_invocation_path("tests/expected/hash/success/outfiles/hashlog");
     }
 
+    run_test("hash/relative/target_dir/is_file",        &["hash", "--relative", &path_in_tests("test1.txt"), "hashlog"])?;
+    run_test("hash/relative/target_dir/nonexistent_dir",&["hash", "--relative", "does_not_exist/", "hashlog"])?;
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target"),
+            &path_in_tests("expected/hash/relative/success/outfiles")
+        );
+        run_test("hash/relative/success", &["hash", "--relative", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/relative/success/outfiles/hashlog");
+    }
+
+    run_test("hash/algorithms/invalid",             &["hash", "--algorithms", "md5,sha512", ".", "hashlog"])?;
+    run_test("hash/algorithms/repeated",            &["hash", "--algorithms", "md5,sha1,md5", ".", "hashlog"])?;
+    run_test("hash/jobs/zero",                      &["hash", "--jobs", "0", ".", "hashlog"])?;
+    run_test("hash/file_types/invalid",             &["hash", "--file-types", "fx", ".", "hashlog"])?;
+    run_test("hash/file_types/empty",               &["hash", "--file-types", "", ".", "hashlog"])?;
+    run_test("hash/hashdeep_binary/not_hashdeep",   &["hash", "--hashdeep-binary", "md5deep", ".", "hashlog"])?;
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target"),
+            &path_in_tests("expected/hash/algorithms/success/outfiles")
+        );
+        run_test("hash/algorithms/success", &["hash", "--algorithms", "sha1,md5", "--jobs", "2", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/algorithms/success/outfiles/hashlog");
+    }
+
+    //hash subcommand tests (native engine)
+    run_test("hash/native/invalid_engine", &["hash", "--engine", "invalid", ".", "hashlog"])?;
+    run_test("hash/native/target_dir/nonexistent_dir", &["hash", "--engine", "native", "does_not_exist/", "hashlog"])?;
+    remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/target_dir/nonexistent_dir/outfiles/hashlog");
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("test1.txt"),
+            &path_in_tests("expected/hash/native/target_dir/is_file/outfiles")
+        );
+        run_test("hash/native/target_dir/is_file", &["hash", "--engine", "native", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/target_dir/is_file/outfiles/hashlog");
+    }
+
+    create_path_and_file("tests/expected/hash/native/output_path_base/log_file_exists/outfiles/hashlog", "");
+    run_test("hash/native/output_path_base/log_file_exists", &["hash", "--engine", "native", ".", "hashlog"])?;
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target_nontrivial"),
+            &path_in_tests("expected/hash/native/success/outfiles")
+        );
+        run_test("hash/native/success", &["hash", "--engine", "native", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/success/outfiles/hashlog");
+    }
+
+    run_test("hash/native/jobs/zero",       &["hash", "--engine", "native", "--jobs", "0", ".", "hashlog"])?;
+    run_test("hash/native/max_reads/zero",  &["hash", "--engine", "native", "--max-reads", "0", ".", "hashlog"])?;
+    run_test("hash/native/max_reads/with_hashdeep_engine", &["hash", "--max-reads", "2", ".", "hashlog"])?;
+    run_test("hash/native/previous/with_hashdeep_engine",
+             &["hash", "--previous", &path_in_tests("test1.txt"), ".", "hashlog"])?;
+    run_test("hash/native/previous/nonexistent_log",
+             &["hash", "--engine", "native", "--previous", "does_not_exist", ".", "hashlog"])?;
+    run_test("hash/native/previous/no_mtimes_file",
+             &["hash", "--engine", "native", "--previous", &path_in_tests("test1.txt"), ".", "hashlog"])?;
+    run_test("hash/native/file_types/with_native_engine", &["hash", "--engine", "native", "--file-types", "fl", ".", "hashlog"])?;
+    run_test("hash/native/hashdeep_binary/with_native_engine",
+             &["hash", "--engine", "native", "--hashdeep-binary", "hashdeep64", ".", "hashlog"])?;
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target_nontrivial"),
+            &path_in_tests("expected/hash/native/jobs/success/outfiles")
+        );
+        run_test("hash/native/jobs/success", &["hash", "--engine", "native", "--jobs", "4", "--max-reads", "2", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/jobs/success/outfiles/hashlog");
+    }
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target_nontrivial"),
+            &path_in_tests("expected/hash/native/relative/success/outfiles")
+        );
+        run_test("hash/native/relative/success", &["hash", "--engine", "native", "--relative", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/relative/success/outfiles/hashlog");
+    }
+
+    {
+        let rel_path = relative_path(
+            &path_in_tests("hashdeep_target_nontrivial"),
+            &path_in_tests("expected/hash/native/algorithms/success/outfiles")
+        );
+        run_test("hash/native/algorithms/success",
+                 &["hash", "--engine", "native", "--algorithms", "whirlpool,tiger,sha1", &rel_path, "hashlog"])?;
+
+        remove_hashdeep_log_header_invocation_path("tests/expected/hash/native/algorithms/success/outfiles/hashlog");
+    }
+
+
+    //hash-archive subcommand tests
+    run_test("hash_archive/0_arguments",    &["hash-archive"])?;
+    run_test("hash_archive/1_argument",     &["hash-archive", "arg1"])?;
+    run_test("hash_archive/archive/nonexistent_file", &["hash-archive", "does_not_exist", "hashlog"])?;
+    run_test("hash_archive/archive/not_an_archive",
+             &["hash-archive", "../../../../../test1.txt", "hashlog"])?;
+
+    create_path_and_file("tests/expected/hash_archive/output_file/file_exists/outfiles/hashlog", "");
+    run_test("hash_archive/output_file/file_exists",
+             &["hash-archive", &path_in_tests("archive_files/nontrivial.tar"), "hashlog"])?;
+
+    for (subdir, archive, options) in [
+        ("tar/success",          "nontrivial.tar",    &[][..]),
+        ("tar/relative/success", "nontrivial.tar",    &["--relative"][..]),
+        ("tar_gz/success",       "nontrivial.tar.gz", &["--algorithms", "sha1,md5"][..]),
+        ("zip/success",          "nontrivial.zip",    &[][..]),
+        ("zip/relative/success", "nontrivial.zip",    &["--relative"][..]),
+    ] {
+        let rel_path = relative_path(
+            &path_in_tests(&format!("archive_files/{archive}")),
+            &path_in_tests(&format!("expected/hash_archive/{subdir}/outfiles"))
+        );
+        let args = [&["hash-archive"][..], options, &[rel_path.as_str(), "hashlog"]].concat();
+        run_test(&format!("hash_archive/{subdir}"), &args)?;
+
+        remove_hashdeep_log_header_invocation_path(&format!("tests/expected/hash_archive/{subdir}/outfiles/hashlog"));
+    }
+
 
     //sort subcommand tests
     run_test("sort/0_arguments",    &["sort"])?;
@@ -212,6 +347,33 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
     run_test("sort/success",                &["sort", &path_in_tests("test1.txt"),        "test1_sorted.txt"])?;
     run_test("sort/success_already_sorted", &["sort", &path_in_tests("test1 sorted.txt"), "test1_sorted.txt"])?;
 
+    run_test("sort/success_with_other_hash_columns",
+             &["sort", &path_in_tests("sort_files/test1_sha1_tiger_whirlpool.txt"), "test1_sorted.txt"])?;
+    run_test("sort/success_with_non_utf8_filenames",
+             &["sort", &path_in_tests("sort_files/test1_non_utf8_filenames.txt"), "test1_sorted.txt"])?;
+
+    run_test("sort/key/invalid", &["sort", "--key", "date", &path_in_tests("sort_files/test_sort_keys.txt"), "sorted.txt"])?;
+    for (subdir, key_args) in [
+        ("name",            &["--key", "name"][..]),
+        ("name_reverse",    &["--reverse"][..]),
+        ("hashes",          &["--key", "hashes"][..]),
+        ("size",            &["--key", "size"][..]),
+        ("size_reverse",    &["--key", "size", "--reverse"][..]),
+        ("path",            &["--key", "path"][..]),
+        ("natural",         &["--key", "natural"][..]),
+        ("natural_reverse", &["--key", "natural", "--reverse"][..]),
+    ] {
+        let input = path_in_tests("sort_files/test_sort_keys.txt");
+        let args = [&["sort"][..], key_args, &[input.as_str(), "sorted.txt"]].concat();
+        run_test(&format!("sort/key/{subdir}"), &args)?;
+    }
+
+    run_test("sort/memory_limit/invalid", &["sort", "--memory-limit", "1X", &path_in_tests("test1.txt"), "test1_sorted.txt"])?;
+    run_test("sort/memory_limit/success",
+             &["sort", "--memory-limit", "1K", "--temp-dir", ".", &path_in_tests("test1.txt"), "test1_sorted.txt"])?;
+    run_test("sort/memory_limit/temp_dir_nonexistent",
+             &["sort", "--memory-limit", "1", "--temp-dir", "does_not_exist", &path_in_tests("test1.txt"), "test1_sorted.txt"])?;
+
     run_test("sort/success_with_log_warnings/unexpected_version_string",
              &["sort", &path_in_tests("sort_files/test1_unexpected_version_string.txt"), "test1_sorted.txt"])?;
     run_test("sort/success_with_log_warnings/header_not_found",
@@ -256,11 +418,17 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
     run_test("root/success/no_entries_match",
              &["root", &path_in_tests("root_files/test1_success_no_entries_match.txt"), "test1_root.txt", "hashdeepComp/"])?;
     run_test("root/success/split_path_component",
+             &["root", "--raw", &path_in_tests("root_files/test1_success_split_path_component.txt"), "test1_root.txt", "hashdeep"])?;
+    run_test("root/split_path_component_rejected",
              &["root", &path_in_tests("root_files/test1_success_split_path_component.txt"), "test1_root.txt", "hashdeep"])?;
+    run_test("root/success/normalized_prefix",
+             &["root", &path_in_tests("test1.txt"), "test1_root.txt", "./hashdeepComp//"])?;
     run_test("root/success/empty_prefix",
              &["root", &path_in_tests("root_files/test1_success_empty_prefix.txt"), "test1_root.txt", ""])?;
     run_test("root/success/no_entries_in_input",
              &["root", &path_in_tests("root_files/test1_success_no_entries_in_input.txt"), "test1_root.txt", "hashdeepComp/"])?;
+    run_test("root/success/non_utf8_filenames",
+             &["root", &path_in_tests("root_files/test1_non_utf8_filenames.txt"), "test1_root.txt", "hashdeepComp/"])?;
 
     run_test("root/success_with_log_warnings/unexpected_version_string",
              &["root", &path_in_tests("root_files/test1_unexpected_version_string.txt"), "test1_root.txt", "hashdeepComp/"])?;
@@ -278,6 +446,39 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
              &["root", &path_in_tests("root_files/test1_unexpected_5th_line_content.txt"), "test1_root.txt", "hashdeepComp/"])?;
 
 
+    //rewrite subcommand tests
+    run_test("rewrite/0_arguments", &["rewrite"])?;
+    run_test("rewrite/1_argument",  &["rewrite", "arg1"])?;
+    run_test("rewrite/no_rules",    &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt"])?;
+
+    run_test("rewrite/input_file/nonexistent_file", &["rewrite", "does_not_exist", "test1_rewritten.txt", "add", "x"])?;
+
+    create_path_and_file("tests/expected/rewrite/output_file/exists/outfiles/test1_rewritten.txt", "");
+    run_test("rewrite/output_file/exists", &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt", "add", "x"])?;
+
+    run_test("rewrite/invalid_rule/unknown",      &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt", "remove", "x"])?;
+    run_test("rewrite/invalid_rule/missing_args", &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt", "replace", "x"])?;
+    run_test("rewrite/invalid_rule/regex",        &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt", "regex", "(", "x"])?;
+    run_test("rewrite/invalid_rule/rules_file",
+             &["rewrite", "--rules-file", "../../../../../rewrite_files/invalid_rules.txt", &path_in_tests("test1.txt"), "test1_rewritten.txt"])?;
+    run_test("rewrite/rules_file_nonexistent",
+             &["rewrite", "--rules-file", "does_not_exist", &path_in_tests("test1.txt"), "test1_rewritten.txt"])?;
+
+    run_test("rewrite/success/add", &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt", "add", "/mnt/volume"])?;
+    run_test("rewrite/success/ordered_rules",
+             &["rewrite", &path_in_tests("test1.txt"), "test1_rewritten.txt",
+               "replace", "./hashdeepComp", "new", "regex", "^rename(Group)", "${1}s/", "replace", "hashdeepComp", "unused"])?;
+    run_test("rewrite/success/rules_file",
+             &["rewrite", "--rules-file", &path_in_tests("rewrite_files/rules.txt"), &path_in_tests("test1.txt"), "test1_rewritten.txt"])?;
+    run_test("rewrite/success/rules_file_drop_unmatched",
+             &["rewrite", "--drop-unmatched", "--rules-file", &path_in_tests("rewrite_files/rules.txt"), &path_in_tests("test1.txt"), "test1_rewritten.txt"])?;
+    run_test("rewrite/success/no_entries_match",
+             &["rewrite", "--drop-unmatched", &path_in_tests("test1.txt"), "test1_rewritten.txt", "replace", "other", "x"])?;
+    run_test("rewrite/success/non_utf8_filenames",
+             &["rewrite", &path_in_tests("root_files/test1_non_utf8_filenames.txt"), "test1_rewritten.txt", "replace", "hashdeepComp", "new"])?;
+    run_test("rewrite/success_with_log_warnings/invalid_log_entry",
+             &["rewrite", &path_in_tests("root_files/test1_invalid_log_entry.txt"), "test1_rewritten.txt", "add", "x"])?;
+
     //part subcommand tests
     run_test("part/0_arguments",    &["part"])?;
     run_test("part/1_argument",     &["part", "arg1"])?;
@@ -354,9 +555,197 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
     part_test("file_rename")?;
     part_test("dir_move")?;
     part_test("dir_rename")?;
+    part_test("directory_moves")?;
     part_test("file_edit")?;
     part_test("file_create")?;
     part_test("file_delete")?;
+    part_test("root_alignment")?;
+    run_test("part/root_alignment_applied", &["part", "--align-roots",
+        &path_in_tests("part_files/root_alignment_file1"),
+        &path_in_tests("part_files/root_alignment_file2"),
+        "part"
+    ])?;
+
+    run_test("part/prefix_rewrite/replace_prefix", &["part", "--replace-prefix1", "targetdir", ".",
+        &path_in_tests("part_files/root_alignment_file1"),
+        &path_in_tests("part_files/root_alignment_file2"),
+        "part"
+    ])?;
+    run_test("part/prefix_rewrite/root", &["part", "--root1", "targetdir/b_dir", "--root2", "./b_dir",
+        &path_in_tests("part_files/root_alignment_file1"),
+        &path_in_tests("part_files/root_alignment_file2"),
+        "part"
+    ])?;
+    run_test("part/prefix_rewrite/root_and_replace_prefix", &["part", "--root1", "a", "--replace-prefix1", "b", "c",
+        &path_in_tests("part_files/root_alignment_file1"),
+        &path_in_tests("part_files/root_alignment_file2"),
+        "part"
+    ])?;
+    run_test("part/prefix_rewrite/replace_prefix_1_value", &["part",
+        &path_in_tests("part_files/root_alignment_file1"),
+        &path_in_tests("part_files/root_alignment_file2"),
+        "part",
+        "--replace-prefix2", "b",
+    ])?;
+
+    //relative input paths: the report lists them as specified
+    run_test("part/json_report/dir_move", &["part", "--json", "report.json",
+        "../../../../../part_files/dir_move_file1",
+        "../../../../../part_files/dir_move_file2",
+        "part"
+    ])?;
+    run_test("part/json_report/general_test_with_log_warnings", &["part", "--json", "report.json",
+        "../../../../../part_files/general_test_with_log_warnings_file1",
+        "../../../../../part_files/general_test_with_log_warnings_file2",
+        "part"
+    ])?;
+    run_test("part/json_report/non_utf8_filenames", &["part", "--json", "report.json",
+        "../../../../../part_files/non_utf8_filenames_file1",
+        "../../../../../part_files/non_utf8_filenames_file2",
+        "part"
+    ])?;
+    create_path_and_file("tests/expected/part/json_report/output_file_exists/outfiles/report.json", "");
+    run_test("part/json_report/output_file_exists", &["part", "--json", "report.json",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+        "part"
+    ])?;
+
+    run_test("part/summary/general_test", &["part", "--summary",
+        &path_in_tests("part_files/general_test_file1"),
+        &path_in_tests("part_files/general_test_file2"),
+        "part"
+    ])?;
+    run_test("part/summary/limit_and_offset", &["part", "--summary", "--summary-limit", "3", "--summary-offset", "2",
+        &path_in_tests("part_files/general_test_file1"),
+        &path_in_tests("part_files/general_test_file2"),
+        "part"
+    ])?;
+    run_test("part/summary/no_changes", &["part", "--summary",
+        &path_in_tests("part_files/1_full_match_pair_file1"),
+        &path_in_tests("part_files/1_full_match_pair_file2"),
+        "part"
+    ])?;
+    run_test("part/summary/limit_without_summary", &["part", "--summary-limit", "3",
+        &path_in_tests("part_files/general_test_file1"),
+        &path_in_tests("part_files/general_test_file2"),
+        "part"
+    ])?;
+
+    run_test("part/exit_zero/differences", &["part", "--exit-zero",
+        &path_in_tests("part_files/file_edit_file1"),
+        &path_in_tests("part_files/file_edit_file2"),
+        "part"
+    ])?;
+    run_test("part/exit_zero/anomalies", &["part", "--exit-zero",
+        &path_in_tests("part_files/general_test_file1"),
+        &path_in_tests("part_files/general_test_file2"),
+        "part"
+    ])?;
+
+    run_test("part/output_dir/new", &["part", "--output-dir", "results/nested",
+        &path_in_tests("part_files/general_test_file1"),
+        &path_in_tests("part_files/general_test_file2"),
+    ])?;
+    run_test("part/output_dir/skip_empty", &["part", "--output-dir", "results", "--skip-empty",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+    run_test("part/skip_empty", &["part", "--skip-empty",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+        "part"
+    ])?;
+    create_path_and_file("tests/expected/part/output_dir/output_file_exists/outfiles/results/name_match_pairs", "");
+    run_test("part/output_dir/output_file_exists", &["part", "--output-dir", "results",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+    //existing output files are replaced (or removed, if skipped as empty), and other files are kept
+    create_path_and_file("tests/expected/part/output_dir/force/outfiles/results/full_match_pairs", "old");
+    create_path_and_file("tests/expected/part/output_dir/force/outfiles/results/name_match_pairs", "old");
+    create_path_and_file("tests/expected/part/output_dir/force/outfiles/results/notes.txt", "kept");
+    run_test("part/output_dir/force", &["part", "--output-dir", "results", "--force", "--skip-empty",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+    //a failed run (here, with a missing log) leaves existing output files as they were
+    create_path_and_file("tests/expected/part/output_dir/force_missing_input/outfiles/results/full_match_pairs", "old");
+    create_path_and_file("tests/expected/part/output_dir/force_missing_input/outfiles/results/name_match_pairs", "old");
+    run_test("part/output_dir/force_missing_input", &["part", "--output-dir", "results", "--force",
+        "../../../../../part_files/nonexistent",
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+    create_path_and_file("tests/expected/part/output_dir/is_a_file/outfiles/results", "");
+    run_test("part/output_dir/is_a_file", &["part", "--output-dir", "results",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+    run_test("part/output_dir/with_output_file_base", &["part", "--output-dir", "results",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+        "part"
+    ])?;
+    run_test("part/output_dir/force_without_output_dir", &["part", "--force",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+        "part"
+    ])?;
+    run_test("part/output_dir/force_and_timestamped", &["part", "--output-dir", "results", "--force", "--timestamped",
+        &path_in_tests("part_files/dir_move_file1"),
+        &path_in_tests("part_files/dir_move_file2"),
+    ])?;
+
+    part_test("different_hash_columns")?;
+    part_test("no_shared_hash_columns")?;
+    part_test("non_utf8_filenames")?;
+
+
+    //audit subcommand tests
+    run_test("audit/0_arguments",    &["audit"])?;
+    run_test("audit/2_arguments",    &["audit", "arg1", "arg2"])?;
+
+    run_test("audit/input_file/nonexistent_file",
+             &["audit", "does_not_exist", &path_in_tests("hashdeep_target_nontrivial"), "audit"])?;
+    run_test("audit/target_dir/is_file_relative",
+             &["audit", "--relative", &path_in_tests("audit_files/nontrivial_relative.txt"), &path_in_tests("test1.txt"), "audit"])?;
+
+    create_path_and_file("tests/expected/audit/output_file_base/file_exists/outfiles/audit_errors", "");
+    run_test("audit/output_file_base/file_exists",
+             &["audit", &path_in_tests("audit_files/nontrivial_relative.txt"), &path_in_tests("hashdeep_target_nontrivial"), "audit"])?;
+
+    run_test("audit/success",
+             &["audit", "--relative", &path_in_tests("audit_files/nontrivial_relative.txt"), &path_in_tests("hashdeep_target_nontrivial"), "audit"])?;
+    run_test("audit/success_skip_unmatchable",
+             &["audit", "--relative", "--skip-unmatchable", "--jobs", "2",
+               &path_in_tests("audit_files/nontrivial_relative.txt"), &path_in_tests("hashdeep_target_nontrivial"), "audit"])?;
+
+
+    //verify subcommand tests
+    run_test("verify/0_arguments",    &["verify"])?;
+    run_test("verify/no_sample_size", &["verify", &path_in_tests("verify_files/unchanged.txt")])?;
+    run_test("verify/percent_and_bytes",
+             &["verify", "--percent", "10", "--bytes", "10", &path_in_tests("verify_files/unchanged.txt")])?;
+    run_test("verify/percent/invalid",
+             &["verify", "--percent", "0", &path_in_tests("verify_files/unchanged.txt")])?;
+    run_test("verify/bytes/invalid",
+             &["verify", "--bytes", "1.5G", &path_in_tests("verify_files/unchanged.txt")])?;
+
+    run_test("verify/input_file/nonexistent_file",
+             &["verify", "--percent", "100", "--seed", "1", "does_not_exist"])?;
+
+    run_test("verify/success/unchanged",
+             &["verify", "--percent", "100", "--seed", "1", "--base-dir", &path_in_tests("hashdeep_target_nontrivial"),
+               &path_in_tests("verify_files/unchanged.txt")])?;
+    run_test("verify/success/changed",
+             &["verify", "--percent", "100", "--seed", "1", "--base-dir", &path_in_tests("hashdeep_target_nontrivial"),
+               &path_in_tests("verify_files/changed.txt")])?;
+    run_test("verify/success/percent_sample",
+             &["verify", "--percent", "50", "--seed", "7", "--jobs", "2", "--base-dir", &path_in_tests("hashdeep_target_nontrivial"),
+               &path_in_tests("verify_files/changed.txt")])?;
+    run_test("verify/success/bytes_sample",
+             &["verify", "--bytes", "12", "--seed", "7", "--base-dir", &path_in_tests("hashdeep_target_nontrivial"),
+               &path_in_tests("verify_files/changed.txt")])?;
 
 
     //multiple-command tests
@@ -367,6 +756,15 @@ fn integration_tests() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::remove_file("tests/expected/multi/hash_then_sort/success/outfiles/hashlog")?;
     remove_hashdeep_log_header_invocation_path("tests/expected/multi/hash_then_sort/success/outfiles/hashlog_sorted");
 
+    //hash a directory and an archive of it, then partition (all entries should be full match pairs)
+    run_test("multi/hash_archive_then_part/success",
+             &["hash", "--engine", "native", "--relative", "../../../../../hashdeep_target_nontrivial", "dirlog"])?;
+    run_test("multi/hash_archive_then_part/success",
+             &["hash-archive", "--relative", "../../../../../archive_files/nontrivial.tar.gz", "archivelog"])?;
+    run_test("multi/hash_archive_then_part/success", &["part", "dirlog", "archivelog", "part"])?;
+    remove_hashdeep_log_header_invocation_path("tests/expected/multi/hash_archive_then_part/success/outfiles/dirlog");
+    remove_hashdeep_log_header_invocation_path("tests/expected/multi/hash_archive_then_part/success/outfiles/archivelog");
+
 
 
     //utility functions